@@ -0,0 +1,377 @@
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use clap::CommandFactory;
+use contract_transcode::ContractMessageTranscoder;
+use scale_info::{PortableRegistry, TypeDef};
+
+use crate::{
+    app_state::AppState,
+    cli::CliCommand,
+    executor::{find_wasm_blob, metadata_path},
+};
+
+/// Signature of a contract message or constructor, as described by the contract metadata.
+pub struct Signature {
+    pub label: String,
+    /// Argument names and types.
+    pub args: Vec<(String, String)>,
+    /// `None` for constructors.
+    pub return_type: Option<String>,
+    pub docs: Vec<String>,
+    pub payable: bool,
+    pub mutates: bool,
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let args = self
+            .args
+            .iter()
+            .map(|(name, ty)| format!("{name}: {ty}"))
+            .collect::<Vec<_>>();
+        write!(f, "{}({})", self.label, args.join(", "))?;
+        if let Some(return_type) = &self.return_type {
+            write!(f, " -> {return_type}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn messages(transcoder: &ContractMessageTranscoder) -> Vec<Signature> {
+    let metadata = transcoder.metadata();
+    let registry = metadata.registry();
+    metadata
+        .spec()
+        .messages()
+        .iter()
+        .map(|message| Signature {
+            label: message.label().clone(),
+            args: message
+                .args()
+                .iter()
+                .map(|arg| (arg.label().clone(), type_name(registry, arg.ty().ty().id)))
+                .collect(),
+            return_type: Some(type_name(
+                registry,
+                message.return_type().ret_type().ty().id,
+            )),
+            docs: message.docs().to_vec(),
+            payable: message.payable(),
+            mutates: message.mutates(),
+        })
+        .collect()
+}
+
+pub fn constructors(transcoder: &ContractMessageTranscoder) -> Vec<Signature> {
+    let metadata = transcoder.metadata();
+    let registry = metadata.registry();
+    metadata
+        .spec()
+        .constructors()
+        .iter()
+        .map(|constructor| Signature {
+            label: constructor.label().clone(),
+            args: constructor
+                .args()
+                .iter()
+                .map(|arg| (arg.label().clone(), type_name(registry, arg.ty().ty().id)))
+                .collect(),
+            return_type: None,
+            docs: constructor.docs().to_vec(),
+            payable: false,
+            mutates: true,
+        })
+        .collect()
+}
+
+/// Readable name of a type, including its type parameters (e.g. `Result<bool, LangError>`).
+fn type_name(registry: &PortableRegistry, id: u32) -> String {
+    let Some(ty) = registry.resolve(id) else {
+        return format!("#{id}");
+    };
+
+    let name = match (ty.path.segments.last(), &ty.type_def) {
+        (Some(name), _) => name.clone(),
+        (None, TypeDef::Primitive(primitive)) => format!("{primitive:?}").to_lowercase(),
+        (None, TypeDef::Sequence(sequence)) => {
+            format!("Vec<{}>", type_name(registry, sequence.type_param.id))
+        }
+        (None, TypeDef::Array(array)) => {
+            format!(
+                "[{}; {}]",
+                type_name(registry, array.type_param.id),
+                array.len
+            )
+        }
+        (None, TypeDef::Tuple(tuple)) => format!(
+            "({})",
+            tuple
+                .fields
+                .iter()
+                .map(|field| type_name(registry, field.id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        (None, TypeDef::Compact(compact)) => type_name(registry, compact.type_param.id),
+        (None, _) => format!("#{id}"),
+    };
+
+    let params = ty
+        .type_params
+        .iter()
+        .filter_map(|param| param.ty.as_ref().map(|ty| type_name(registry, ty.id)))
+        .collect::<Vec<_>>();
+    match params.is_empty() {
+        true => name,
+        false => format!("{name}<{}>", params.join(", ")),
+    }
+}
+
+/// The metadata of the contract built in the working directory. Since it is consulted on every
+/// redraw, it is loaded again only when the metadata file changes.
+#[derive(Default)]
+pub struct TranscoderCache {
+    cached: Option<CachedTranscoder>,
+}
+
+struct CachedTranscoder {
+    path: PathBuf,
+    modified: SystemTime,
+    /// `None` if the metadata could not be loaded.
+    transcoder: Option<Arc<ContractMessageTranscoder>>,
+}
+
+impl TranscoderCache {
+    /// The transcoder of the contract built in `cwd` (if any).
+    pub fn get(&mut self, cwd: &Path) -> Option<Arc<ContractMessageTranscoder>> {
+        let (contract_name, _) = find_wasm_blob(cwd)?;
+        let path = metadata_path(cwd, &contract_name);
+        let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+
+        match &self.cached {
+            Some(cached) if cached.path == path && cached.modified == modified => {}
+            _ => {
+                let transcoder = ContractMessageTranscoder::load(&path).ok().map(Arc::new);
+                self.cached = Some(CachedTranscoder {
+                    path,
+                    modified,
+                    transcoder,
+                });
+            }
+        }
+        self.cached.as_ref()?.transcoder.clone()
+    }
+}
+
+/// What is being typed after `call` or `deploy`.
+struct Invocation<'a> {
+    /// The message or constructor name.
+    label: &'a str,
+    /// The number of (possibly incomplete) arguments typed so far.
+    args: usize,
+    is_call: bool,
+}
+
+fn parse_invocation<'a>(words: &[&'a str]) -> Option<Invocation<'a>> {
+    match words {
+        ["call", label, args @ ..] => Some(Invocation {
+            label,
+            args: args.len(),
+            is_call: true,
+        }),
+        ["deploy" | "d", rest @ ..] => {
+            let mut label = "new";
+            let mut args = 0;
+            let mut rest = rest.iter();
+            while let Some(word) = rest.next() {
+                match *word {
+                    "--constructor" => label = *rest.next()?,
+                    "--salt" => {
+                        rest.next()?;
+                    }
+                    _ => args += 1,
+                }
+            }
+            Some(Invocation {
+                label,
+                args,
+                is_call: false,
+            })
+        }
+        _ => None,
+    }
+}
+
+impl AppState {
+    fn message_signatures(&self) -> Vec<Signature> {
+        self.contracts
+            .current_contract()
+            .map(|contract| messages(&contract.transcoder))
+            .unwrap_or_default()
+    }
+
+    fn constructor_signatures(&mut self) -> Vec<Signature> {
+        self.transcoder_cache
+            .get(&self.ui_state.cwd)
+            .map(|transcoder| constructors(&transcoder))
+            .unwrap_or_default()
+    }
+
+    /// Complete the last word of the user input: a command, a message name (after `call`) or a
+    /// constructor name (after `deploy --constructor`). If there are several candidates, the word
+    /// is extended to their common prefix, or the candidates are printed.
+    pub fn complete_input(&mut self) {
+        let input = self.ui_state.user_input.current_input().to_string();
+        let mut words = input.split_ascii_whitespace().collect::<Vec<_>>();
+        if input.ends_with(' ') {
+            words.push("");
+        }
+
+        let candidates = match words.as_slice() {
+            [_] => CliCommand::command()
+                .get_subcommands()
+                .map(|command| command.get_name().to_string())
+                .collect(),
+            ["call", _] => self
+                .message_signatures()
+                .into_iter()
+                .map(|signature| signature.label)
+                .collect(),
+            ["deploy" | "d", .., "--constructor", _] => self
+                .constructor_signatures()
+                .into_iter()
+                .map(|signature| signature.label)
+                .collect(),
+            _ => Vec::<String>::new(),
+        };
+
+        let prefix = words.last().copied().unwrap_or_default();
+        let matching = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect::<Vec<_>>();
+        let base = &input[..input.len() - prefix.len()];
+
+        match matching.as_slice() {
+            [] => {}
+            [single] => self.ui_state.user_input.set(format!("{base}{single} ")),
+            _ => {
+                let common = common_prefix(&matching);
+                match common.len() > prefix.len() {
+                    true => self.ui_state.user_input.set(format!("{base}{common}")),
+                    false => self.print(&matching.join("  ")),
+                }
+            }
+        }
+    }
+
+    /// The arguments (`<name: type>`) that are still to be typed for the message or constructor
+    /// in the user input.
+    pub fn input_hint(&mut self) -> Option<String> {
+        let input = self.ui_state.user_input.current_input().to_string();
+        let words = input.split_ascii_whitespace().collect::<Vec<_>>();
+        let invocation = parse_invocation(&words)?;
+
+        let signatures = match invocation.is_call {
+            true => self.message_signatures(),
+            false => self.constructor_signatures(),
+        };
+        let signature = signatures
+            .into_iter()
+            .find(|signature| signature.label == invocation.label)?;
+
+        let hint = signature
+            .args
+            .iter()
+            .skip(invocation.args)
+            .map(|(name, ty)| format!("<{name}: {ty}>"))
+            .collect::<Vec<_>>();
+        (!hint.is_empty()).then(|| hint.join(" "))
+    }
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let first = &words[0];
+    let len = words[1..].iter().fold(first.chars().count(), |len, word| {
+        first
+            .chars()
+            .zip(word.chars())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(len)
+    });
+    first.chars().take(len).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use scale_info::{meta_type, Registry};
+
+    use super::*;
+
+    fn invocation(input: &str) -> Option<(&str, usize, bool)> {
+        let words = input.split_ascii_whitespace().collect::<Vec<_>>();
+        parse_invocation(&words).map(|inv| (inv.label, inv.args, inv.is_call))
+    }
+
+    #[test]
+    fn invocations_are_parsed() {
+        assert_eq!(invocation("call flip"), Some(("flip", 0, true)));
+        assert_eq!(
+            invocation("call transfer 0x01 12"),
+            Some(("transfer", 2, true))
+        );
+        assert_eq!(invocation("deploy true"), Some(("new", 1, false)));
+        assert_eq!(
+            invocation("d --salt 01 --constructor default"),
+            Some(("default", 0, false))
+        );
+        assert_eq!(
+            invocation("deploy --constructor new_with 1 --salt 01 2"),
+            Some(("new_with", 2, false))
+        );
+    }
+
+    #[test]
+    fn incomplete_or_other_commands_are_not_invocations() {
+        assert_eq!(invocation("call"), None);
+        assert_eq!(invocation("deploy --constructor"), None);
+        assert_eq!(invocation("deploy --salt"), None);
+        assert_eq!(invocation("next-block 2"), None);
+    }
+
+    #[test]
+    fn common_prefix_counts_characters() {
+        let words = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(common_prefix(&words(&["get", "get_value"])), "get");
+        assert_eq!(common_prefix(&words(&["flip", "flop", "fly"])), "fl");
+        assert_eq!(common_prefix(&words(&["set", "get"])), "");
+        assert_eq!(common_prefix(&words(&["żółw"])), "żółw");
+        assert_eq!(common_prefix(&words(&["żółw", "żółty"])), "żół");
+    }
+
+    #[test]
+    fn type_names_are_readable() {
+        let mut registry = Registry::new();
+        let result = registry.register_type(&meta_type::<Result<bool, u8>>()).id;
+        let vec = registry.register_type(&meta_type::<Vec<u32>>()).id;
+        let array = registry.register_type(&meta_type::<[u8; 4]>()).id;
+        let tuple = registry
+            .register_type(&meta_type::<(u64, Option<bool>)>())
+            .id;
+        let registry = PortableRegistry::from(registry);
+
+        assert_eq!(type_name(&registry, result), "Result<bool, u8>");
+        assert_eq!(type_name(&registry, vec), "Vec<u32>");
+        assert_eq!(type_name(&registry, array), "[u8; 4]");
+        assert_eq!(type_name(&registry, tuple), "(u64, Option<bool>)");
+        assert_eq!(type_name(&registry, 1000), "#1000");
+    }
+}
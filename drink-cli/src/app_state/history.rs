@@ -0,0 +1,27 @@
+use drink::{AccountId32, Weight};
+
+/// A command that affected the session, kept so that the session can be exported as a test.
+pub enum HistoryEntry {
+    SetActor(AccountId32),
+    SetGasLimit(Weight),
+    NextBlock(u32),
+    AddTokens {
+        recipient: AccountId32,
+        value: u128,
+    },
+    Deploy {
+        contract_name: String,
+        constructor: String,
+        args: Vec<String>,
+        salt: Vec<u8>,
+        /// The address of the deployed contract, `None` if the deployment failed.
+        address: Option<AccountId32>,
+    },
+    Call {
+        address: AccountId32,
+        message: String,
+        args: Vec<String>,
+        /// The decoded result or the error description.
+        outcome: Result<String, String>,
+    },
+}
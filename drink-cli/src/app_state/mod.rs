@@ -1,12 +1,17 @@
 use std::{env, path::PathBuf};
 
+pub use completion::{messages, TranscoderCache};
 pub use contracts::{Contract, ContractIndex, ContractRegistry};
 use drink::{minimal::MinimalSandbox, session::Session, AccountId32, Sandbox, Weight};
+pub use history::HistoryEntry;
+pub use print::{CommandReport, ReportLine};
 pub use user_input::UserInput;
 
 use crate::app_state::output::Output;
 
+mod completion;
 mod contracts;
+mod history;
 mod output;
 pub mod print;
 mod user_input;
@@ -72,6 +77,14 @@ pub struct AppState {
     pub chain_info: ChainInfo,
     pub ui_state: UiState,
     pub contracts: ContractRegistry,
+    /// Outcome of the last deployment or call: the decoded result or the error description.
+    pub last_outcome: Option<Result<String, String>>,
+    /// What the command being executed has printed so far.
+    pub report: CommandReport,
+    /// Commands that affected the session, in the order of execution.
+    pub history: Vec<HistoryEntry>,
+    /// Metadata of the contract built in the working directory, used for input completion.
+    pub transcoder_cache: TranscoderCache,
 }
 
 impl AppState {
@@ -81,6 +94,10 @@ impl AppState {
             chain_info: Default::default(),
             ui_state: UiState::new(cwd_override),
             contracts: Default::default(),
+            last_outcome: None,
+            report: Default::default(),
+            history: vec![],
+            transcoder_cache: Default::default(),
         }
     }
 }
@@ -6,6 +6,28 @@ use ratatui::{
 
 use crate::app_state::AppState;
 
+/// A line printed by a command.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ReportLine {
+    Info(String),
+    Error(String),
+}
+
+/// Everything that a single command printed, in order.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct CommandReport {
+    pub lines: Vec<ReportLine>,
+}
+
+impl CommandReport {
+    /// Whether the command reported any error (including failed assertions).
+    pub fn failed(&self) -> bool {
+        self.lines
+            .iter()
+            .any(|line| matches!(line, ReportLine::Error(_)))
+    }
+}
+
 impl AppState {
     pub fn print_command(&mut self, command: &str) {
         self.ui_state.output.push("".into());
@@ -22,6 +44,7 @@ impl AppState {
     }
 
     pub fn print(&mut self, msg: &str) {
+        self.report.lines.push(ReportLine::Info(msg.to_string()));
         self.print_sequence(
             msg.split('\n'),
             Style::default()
@@ -31,6 +54,7 @@ impl AppState {
     }
 
     pub fn print_error(&mut self, err: &str) {
+        self.report.lines.push(ReportLine::Error(err.to_string()));
         self.print_sequence(
             err.split('\n'),
             Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
@@ -0,0 +1,113 @@
+//! Non-interactive mode: executing commands from a script or the standard input.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    app_state::{AppState, ReportLine},
+    executor::execute_command,
+};
+
+/// Executes the commands from `script` (or the standard input, if `script` is `None`) one by one,
+/// printing their results to the standard output. Empty lines and lines starting with `#` are
+/// skipped. Stops with an error at the first command that fails (including failed assertions).
+pub fn run_batch(cwd_override: Option<PathBuf>, script: Option<PathBuf>) -> Result<()> {
+    let content = match &script {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?,
+        None => {
+            let mut content = String::new();
+            io::stdin()
+                .read_to_string(&mut content)
+                .context("Failed to read the standard input")?;
+            content
+        }
+    };
+
+    run_script(
+        &mut AppState::new(cwd_override),
+        &content,
+        &mut io::stdout(),
+    )
+}
+
+/// Executes the commands from `script`, writing their results to `out`. See `run_batch`.
+fn run_script(app_state: &mut AppState, script: &str, out: &mut impl Write) -> Result<()> {
+    for (index, line) in script.lines().enumerate() {
+        let command = line.trim();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+
+        let report = execute_command(app_state, command)?;
+        for line in &report.lines {
+            match line {
+                ReportLine::Info(text) => writeln!(out, "{text}")?,
+                ReportLine::Error(text) => writeln!(out, "error: {text}")?,
+            }
+        }
+
+        if report.failed() {
+            bail!("Command `{command}` (line {}) failed", index + 1);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(script: &str) -> (AppState, Result<()>, String) {
+        let mut app_state = AppState::default();
+        let mut out = vec![];
+        let result = run_script(&mut app_state, script, &mut out);
+        (app_state, result, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn all_commands_are_executed() {
+        let (app_state, result, out) = run("
+            # Comments and empty lines are skipped.
+
+            next-block 2
+            set-gas-limit 100 200
+        ");
+
+        assert!(result.is_ok());
+        assert_eq!(out, "2 blocks built\nGas limit was set\n");
+        // The sandbox starts at block 1.
+        assert_eq!(app_state.chain_info.block_height, 3);
+    }
+
+    #[test]
+    fn failed_assertion_stops_the_script() {
+        let (app_state, result, out) = run("next-block\nassert-failure\nnext-block");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Command `assert-failure` (line 2) failed"
+        );
+        assert_eq!(
+            out,
+            "1 blocks built\nerror: Assertion failed: no deployment or call was made\n"
+        );
+        assert_eq!(app_state.chain_info.block_height, 2);
+    }
+
+    #[test]
+    fn invalid_command_stops_the_script() {
+        let (_, result, out) = run("drink");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Command `drink` (line 1) failed"
+        );
+        assert_eq!(out, "error: Invalid command\n");
+    }
+}
@@ -43,6 +43,20 @@ pub enum CliCommand {
         message: String,
         args: Vec<String>,
     },
+
+    AssertReturn {
+        expected: Vec<String>,
+    },
+    AssertFailure,
+
+    #[clap(alias = "m")]
+    Messages,
+
+    ExportTest {
+        path: String,
+        #[clap(long, default_value = "exported_session")]
+        name: String,
+    },
 }
 
 #[cfg(test)]
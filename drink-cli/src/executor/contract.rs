@@ -6,9 +6,17 @@ use std::{
 
 use contract_build::{BuildMode, ExecuteArgs, ManifestPath, OptimizationPasses, Verbosity};
 use contract_transcode::ContractMessageTranscoder;
+use drink::{
+    minimal::{MinimalSandboxRuntime, RuntimeEvent},
+    pallet_contracts,
+    session::error::SessionError,
+    AccountId32,
+};
+use ink_sandbox::EventRecordOf;
+use parity_scale_codec::Encode;
 
 use crate::{
-    app_state::{print::format_contract_action, AppState, Contract},
+    app_state::{print::format_contract_action, AppState, Contract, HistoryEntry},
     executor::error::BuildError,
 };
 
@@ -62,10 +70,7 @@ pub fn deploy(app_state: &mut AppState, constructor: String, args: Vec<String>,
     };
 
     // Read contract metadata and prepare transcoder
-    let metadata_path = app_state
-        .ui_state
-        .cwd
-        .join(format!("target/ink/{contract_name}.json"));
+    let metadata_path = metadata_path(&app_state.ui_state.cwd, &contract_name);
 
     let Ok(transcoder) = ContractMessageTranscoder::load(metadata_path) else {
         app_state.print_error("Failed to create transcoder from metadata file.");
@@ -73,24 +78,38 @@ pub fn deploy(app_state: &mut AppState, constructor: String, args: Vec<String>,
     };
     let transcoder = Arc::new(transcoder);
 
-    match app_state.session.deploy(
+    let result = app_state.session.deploy(
         contract_bytes,
         &constructor,
         args.as_slice(),
-        salt,
+        salt.clone(),
         None,
         &transcoder,
-    ) {
+    );
+    app_state.history.push(HistoryEntry::Deploy {
+        contract_name: contract_name.clone(),
+        constructor,
+        args,
+        salt,
+        address: result.as_ref().ok().cloned(),
+    });
+
+    match result {
         Ok(address) => {
+            app_state.last_outcome = Some(Ok(address.to_string()));
             app_state.contracts.add(Contract {
                 name: contract_name,
-                address,
+                address: address.clone(),
                 base_path: app_state.ui_state.cwd.clone(),
-                transcoder,
+                transcoder: transcoder.clone(),
             });
             app_state.print("Contract deployed successfully");
+            print_events(app_state, &address, &transcoder);
+        }
+        Err(err) => {
+            app_state.last_outcome = Some(Err(err.to_string()));
+            app_state.print_error(&format!("Failed to deploy contract\n{err}"))
         }
-        Err(err) => app_state.print_error(&format!("Failed to deploy contract\n{err}")),
     }
 
     if let Some(info) = app_state.session.record().deploy_results().last() {
@@ -105,20 +124,107 @@ pub fn call(app_state: &mut AppState, message: String, args: Vec<String>) {
     };
 
     let address = contract.address.clone();
-    match app_state
-        .session
-        .call_with_address::<_, ()>(address, &message, &args, None)
-    {
-        Ok(result) => app_state.print(&format!("Result: {:?}", result)),
-        Err(err) => app_state.print_error(&format!("Failed to call contract\n{err}")),
-    };
+    let transcoder = contract.transcoder.clone();
+    let outcome =
+        match app_state
+            .session
+            .call_with_address::<_, ()>(address.clone(), &message, &args, None)
+        {
+            Ok(_) => {
+                let result = decode_return(
+                    &transcoder,
+                    &message,
+                    app_state.session.record().last_call_return(),
+                );
+                app_state.print(&format!("Result: {result}"));
+                print_events(app_state, &address, &transcoder);
+                Ok(result)
+            }
+            Err(SessionError::CallReverted(data)) => {
+                let result = decode_return(&transcoder, &message, &data);
+                app_state.print_error(&format!(
+                    "Contract call has been reverted\nResult: {result}"
+                ));
+                Err(format!("Reverted with {result}"))
+            }
+            Err(err) => {
+                app_state.print_error(&format!("Failed to call contract\n{err}"));
+                Err(err.to_string())
+            }
+        };
+    app_state.last_outcome = Some(outcome.clone());
+    app_state.history.push(HistoryEntry::Call {
+        address,
+        message,
+        args,
+        outcome,
+    });
 
     if let Some(info) = app_state.session.record().call_results().last() {
         app_state.print(&format_contract_action(info))
     }
 }
 
-fn find_wasm_blob(cwd: &Path) -> Option<(String, PathBuf)> {
+/// Decode the value returned (or reverted with) by `message`.
+fn decode_return(transcoder: &ContractMessageTranscoder, message: &str, data: &[u8]) -> String {
+    transcoder
+        .decode_message_return(message, &mut &*data)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|err| format!("<undecodable: {err}>"))
+}
+
+/// Print the events emitted by the contract at `address` during the last deployment or call.
+fn print_events(
+    app_state: &mut AppState,
+    address: &AccountId32,
+    transcoder: &ContractMessageTranscoder,
+) {
+    let events = decode_events(
+        app_state.session.record().last_event_batch().all_events(),
+        address,
+        transcoder,
+    );
+    if events.is_empty() {
+        return;
+    }
+    app_state.print("Events:");
+    for event in events {
+        app_state.print(&format!("  {event}"));
+    }
+}
+
+/// Decode the events emitted by the contract at `address`. Events of the other contracts involved
+/// in the interaction are skipped, since `transcoder` does not describe them.
+fn decode_events(
+    events: &[EventRecordOf<MinimalSandboxRuntime>],
+    address: &AccountId32,
+    transcoder: &ContractMessageTranscoder,
+) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|record| match &record.event {
+            RuntimeEvent::Contracts(pallet_contracts::Event::ContractEmitted {
+                contract,
+                data,
+            }) if contract == address => Some((record.topics.first(), data)),
+            _ => None,
+        })
+        .map(|(signature_topic, data)| {
+            let Some(signature_topic) = signature_topic else {
+                return "<undecodable: anonymous event>".to_string();
+            };
+            // The transcoder expects the data as it is encoded in the runtime event, i.e. with
+            // the length prefix.
+            transcoder
+                .decode_contract_event(signature_topic, &mut &*data.encode())
+                .map(|value| value.to_string())
+                .unwrap_or_else(|err| format!("<undecodable: {err}>"))
+        })
+        .collect()
+}
+
+/// Find the contract code built in `cwd`. Returns the contract name and the path to the code.
+pub fn find_wasm_blob(cwd: &Path) -> Option<(String, PathBuf)> {
     let Ok(entries) = fs::read_dir(cwd.join("target/ink")) else {
         return None;
     };
@@ -137,3 +243,104 @@ fn find_wasm_blob(cwd: &Path) -> Option<(String, PathBuf)> {
 
     Some((raw_name, file.path()))
 }
+
+/// Path to the metadata of the contract `contract_name` built in `cwd`.
+pub fn metadata_path(cwd: &Path, contract_name: &str) -> PathBuf {
+    cwd.join(format!("target/ink/{contract_name}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use ink_metadata::{
+        layout::{Layout, LayoutKey, LeafLayout},
+        ConstructorSpec, ContractSpec, EnvironmentSpec, EventParamSpec, EventSpec, InkProject,
+        MessageSpec, ReturnTypeSpec, TypeSpec,
+    };
+    use ink_sandbox::frame_system::{EventRecord, Phase};
+
+    use super::*;
+
+    const FLIPPED_TOPIC: [u8; 32] = [1; 32];
+
+    /// Transcoder for a flipper-like contract with a single `Flipped { value: bool }` event.
+    fn transcoder() -> ContractMessageTranscoder {
+        let environment = EnvironmentSpec::new()
+            .account_id(TypeSpec::of_type::<[u8; 32]>())
+            .balance(TypeSpec::of_type::<u128>())
+            .hash(TypeSpec::of_type::<[u8; 32]>())
+            .timestamp(TypeSpec::of_type::<u64>())
+            .block_number(TypeSpec::of_type::<u32>())
+            .chain_extension(TypeSpec::of_type::<()>())
+            .max_event_topics(4)
+            .static_buffer_size(16384)
+            .done();
+        let spec = ContractSpec::new()
+            .constructors([ConstructorSpec::from_label("new")
+                .selector([0; 4])
+                .payable(false)
+                .returns(ReturnTypeSpec::new(TypeSpec::of_type::<()>()))
+                .done()])
+            .messages([MessageSpec::from_label("flip")
+                .selector([1; 4])
+                .mutates(true)
+                .payable(false)
+                .returns(ReturnTypeSpec::new(TypeSpec::of_type::<()>()))
+                .done()])
+            .events([EventSpec::new("Flipped")
+                .signature_topic(Some(FLIPPED_TOPIC))
+                .args([EventParamSpec::new("value")
+                    .of_type(TypeSpec::of_type::<bool>())
+                    .done()])
+                .done()])
+            .environment(environment)
+            .done();
+        let layout = Layout::Leaf(LeafLayout::from_key::<bool>(LayoutKey::new(0u32)));
+
+        ContractMessageTranscoder::new(InkProject::new(layout, spec))
+    }
+
+    fn emitted(
+        contract: &AccountId32,
+        topics: Vec<[u8; 32]>,
+        data: Vec<u8>,
+    ) -> EventRecordOf<MinimalSandboxRuntime> {
+        EventRecord {
+            phase: Phase::Initialization,
+            event: RuntimeEvent::Contracts(pallet_contracts::Event::ContractEmitted {
+                contract: contract.clone(),
+                data,
+            }),
+            topics: topics.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    #[test]
+    fn only_events_of_the_given_contract_are_decoded() {
+        let contract = AccountId32::new([1; 32]);
+        let other_contract = AccountId32::new([2; 32]);
+        let events = [
+            emitted(&contract, vec![FLIPPED_TOPIC], true.encode()),
+            emitted(&other_contract, vec![FLIPPED_TOPIC], false.encode()),
+            emitted(&contract, vec![FLIPPED_TOPIC], false.encode()),
+        ];
+
+        assert_eq!(
+            decode_events(&events, &contract, &transcoder()),
+            ["Flipped { value: true }", "Flipped { value: false }"]
+        );
+    }
+
+    #[test]
+    fn unknown_and_anonymous_events_are_reported() {
+        let contract = AccountId32::new([1; 32]);
+        let events = [
+            emitted(&contract, vec![[2; 32]], true.encode()),
+            emitted(&contract, vec![], true.encode()),
+        ];
+
+        let decoded = decode_events(&events, &contract, &transcoder());
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded[0].starts_with("<undecodable: Event with signature topic"));
+        assert_eq!(decoded[1], "<undecodable: anonymous event>");
+    }
+}
@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, fmt::Write, fs};
+
+use convert_case::{Case, Casing};
+use drink::AccountId32;
+
+use crate::app_state::{AppState, HistoryEntry};
+
+/// Write the session history as a `#[drink::test]` function to the file under `path`.
+pub fn export_test(app_state: &mut AppState, path: String, name: String) {
+    let target = app_state.ui_state.cwd.join(path);
+    match fs::write(&target, generate_test(&app_state.history, &name)) {
+        Ok(_) => app_state.print(&format!("Test exported to {}", target.display())),
+        Err(err) => app_state.print_error(&format!("Failed to export test\n{err}")),
+    }
+}
+
+/// Unwraps the result of `Session::call_with_address` in the generated test. `LangError` doesn't
+/// implement `std::error::Error`, so it is converted into a string first.
+const UNWRAP_MESSAGE_RESULT: &str = "?.map_err(|err| format!(\"{err:?}\"))?";
+
+/// Generate a test file that repeats `history` and checks the observed outcomes.
+fn generate_test(history: &[HistoryEntry], name: &str) -> String {
+    // Contract addresses are different in every session, so we refer to the deployed contracts by
+    // variables: `contract_{i}` (address) and `bundle_{i}` (bundle, for decoding return values).
+    let mut contracts = BTreeMap::<&AccountId32, usize>::new();
+    let mut body = String::new();
+
+    for entry in history {
+        match entry {
+            HistoryEntry::SetActor(actor) => {
+                writeln!(body, "    session.set_actor({});", account(actor))
+            }
+            HistoryEntry::SetGasLimit(gas_limit) => writeln!(
+                body,
+                "    session.set_gas_limit(Weight::from_parts({}, {}));",
+                gas_limit.ref_time(),
+                gas_limit.proof_size()
+            ),
+            HistoryEntry::NextBlock(count) => {
+                writeln!(body, "    session.sandbox().build_blocks({count});")
+            }
+            HistoryEntry::AddTokens { recipient, value } => writeln!(
+                body,
+                "    session\n        .sandbox()\n        .mint_into(&{}, {value})\n        .expect(\"Failed to add tokens\");",
+                account(recipient)
+            ),
+            HistoryEntry::Deploy {
+                contract_name,
+                constructor,
+                args,
+                salt,
+                address,
+            } => {
+                let bundle = format!(
+                    "BundleProvider::{}.bundle()?",
+                    contract_name.to_case(Case::Pascal)
+                );
+                let salt = match salt.is_empty() {
+                    true => "NO_SALT".to_string(),
+                    false => format!("vec!{salt:?}"),
+                };
+                match address {
+                    Some(address) => {
+                        let index = contracts.len();
+                        contracts.insert(address, index);
+                        writeln!(body, "    let bundle_{index} = {bundle};").and_then(|_| {
+                            writeln!(
+                                body,
+                                "    let contract_{index} = session.deploy_bundle(bundle_{index}.clone(), {constructor:?}, {}, {salt}, NO_ENDOWMENT)?;",
+                                arguments(args)
+                            )
+                        })
+                    }
+                    None => writeln!(
+                        body,
+                        "    assert!(session\n        .deploy_bundle({bundle}, {constructor:?}, {}, {salt}, NO_ENDOWMENT)\n        .is_err());",
+                        arguments(args)
+                    ),
+                }
+            }
+            HistoryEntry::Call {
+                address,
+                message,
+                args,
+                outcome,
+            } => {
+                let Some(index) = contracts.get(address) else {
+                    continue;
+                };
+                let call = format!(
+                    "session.call_with_address::<_, ()>(contract_{index}.clone(), {message:?}, {}, NO_ENDOWMENT)",
+                    arguments(args)
+                );
+                match outcome {
+                    Ok(value) if value.starts_with("<undecodable") => {
+                        writeln!(body, "    {call}{UNWRAP_MESSAGE_RESULT};")
+                    }
+                    Ok(value) => writeln!(body, "    {call}{UNWRAP_MESSAGE_RESULT};").and_then(|_| {
+                        writeln!(
+                            body,
+                            "    assert_eq!(\n        decoded_return(&bundle_{index}, {message:?}, session.record().last_call_return())?,\n        {value:?}\n    );"
+                        )
+                    }),
+                    Err(_) => writeln!(body, "    assert!({call}.is_err());"),
+                }
+            }
+        }
+        .expect("Writing to a string cannot fail");
+    }
+
+    format!(
+        r#"//! Generated by `drink-cli export-test`.
+
+use std::error::Error;
+
+#[allow(unused_imports)]
+use drink::{{
+    sandbox_api::prelude::*,
+    session::{{ContractBundle, Session, NO_ARGS, NO_ENDOWMENT, NO_SALT}},
+    AccountId32, Ss58Codec, Weight,
+}};
+
+#[drink::contract_bundle_provider]
+enum BundleProvider {{}}
+
+#[drink::test]
+fn {name}(mut session: Session) -> Result<(), Box<dyn Error>> {{
+{body}
+    Ok(())
+}}
+
+/// Decodes the return value of `message` in the same way as `drink-cli` does.
+#[allow(dead_code)]
+fn decoded_return(
+    bundle: &ContractBundle,
+    message: &str,
+    data: &[u8],
+) -> Result<String, Box<dyn Error>> {{
+    Ok(bundle
+        .transcoder
+        .decode_message_return(message, &mut &*data)?
+        .to_string())
+}}
+"#
+    )
+}
+
+fn account(account: &AccountId32) -> String {
+    format!(
+        "AccountId32::from_ss58check({:?}).expect(\"Invalid address\")",
+        account.to_string()
+    )
+}
+
+fn arguments(args: &[String]) -> String {
+    match args.is_empty() {
+        true => "NO_ARGS".to_string(),
+        false => format!("&{args:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use drink::{
+        minimal::MinimalSandbox,
+        session::{Session, NO_ARGS, NO_ENDOWMENT},
+    };
+
+    use super::*;
+
+    /// Mirrors a call emitted by `generate_test`, so that its shape is type-checked against the
+    /// real `Session` API.
+    #[allow(dead_code)]
+    fn generated_call(
+        session: &mut Session<MinimalSandbox>,
+        contract_0: AccountId32,
+    ) -> Result<(), Box<dyn Error>> {
+        session
+            .call_with_address::<_, ()>(contract_0.clone(), "get", NO_ARGS, NO_ENDOWMENT)?
+            .map_err(|err| format!("{err:?}"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn calls_refer_to_deployed_contracts() {
+        let address = AccountId32::new([1; 32]);
+        let history = vec![
+            HistoryEntry::Deploy {
+                contract_name: "my_flipper".to_string(),
+                constructor: "new".to_string(),
+                args: vec!["true".to_string()],
+                salt: vec![],
+                address: Some(address.clone()),
+            },
+            HistoryEntry::Call {
+                address,
+                message: "get".to_string(),
+                args: vec![],
+                outcome: Ok("Ok(true)".to_string()),
+            },
+        ];
+
+        let test = generate_test(&history, "flipping");
+
+        assert!(test.contains("fn flipping(mut session: Session)"));
+        assert!(test.contains("let bundle_0 = BundleProvider::MyFlipper.bundle()?;"));
+        assert!(test.contains("call_with_address::<_, ()>(contract_0.clone(), \"get\", NO_ARGS"));
+        assert!(test.contains("\"Ok(true)\""));
+    }
+
+    #[test]
+    fn message_results_are_unwrapped_like_in_generated_call() {
+        let address = AccountId32::new([1; 32]);
+        let history = vec![
+            HistoryEntry::Deploy {
+                contract_name: "my_flipper".to_string(),
+                constructor: "new".to_string(),
+                args: vec![],
+                salt: vec![],
+                address: Some(address.clone()),
+            },
+            HistoryEntry::Call {
+                address,
+                message: "get".to_string(),
+                args: vec![],
+                outcome: Ok("<undecodable>".to_string()),
+            },
+        ];
+
+        let test = generate_test(&history, "flipping");
+
+        assert!(test.contains(
+            "    session.call_with_address::<_, ()>(contract_0.clone(), \"get\", NO_ARGS, NO_ENDOWMENT)?.map_err(|err| format!(\"{err:?}\"))?;\n"
+        ));
+        assert!(!test.contains("??"));
+    }
+}
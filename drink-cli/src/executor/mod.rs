@@ -1,18 +1,34 @@
 mod contract;
 mod error;
+mod export;
 
-use std::env;
+use std::{env, mem};
 
 use anyhow::Result;
 use clap::Parser;
+pub use contract::{find_wasm_blob, metadata_path};
 use drink::{sandbox_api::prelude::*, AccountId32, Weight};
 
-use crate::{app_state::AppState, cli::CliCommand};
+use crate::{
+    app_state::{messages, AppState, CommandReport, HistoryEntry},
+    cli::CliCommand,
+};
 
 pub fn execute(app_state: &mut AppState) -> Result<()> {
     let command = app_state.ui_state.user_input.current_input().to_string();
-    app_state.print_command(&command);
+    execute_command(app_state, &command).map(|_| ())
+}
+
+/// Parses and executes a single command, printing its results to the output. Returns everything
+/// that the command printed.
+pub fn execute_command(app_state: &mut AppState, command: &str) -> Result<CommandReport> {
+    app_state.print_command(command);
+    app_state.report = CommandReport::default();
+    run_command(app_state, command)?;
+    Ok(mem::take(&mut app_state.report))
+}
 
+fn run_command(app_state: &mut AppState, command: &str) -> Result<()> {
     let command = command
         .split_ascii_whitespace()
         .map(|a| a.trim())
@@ -42,14 +58,19 @@ pub fn execute(app_state: &mut AppState) -> Result<()> {
         CliCommand::NextBlock { count } => build_blocks(app_state, count),
         CliCommand::AddTokens { recipient, value } => add_tokens(app_state, recipient, value)?,
         CliCommand::SetActor { actor } => {
-            app_state.chain_info.actor = actor;
+            app_state.session.set_actor(actor.clone());
+            app_state.chain_info.actor = actor.clone();
+            app_state.history.push(HistoryEntry::SetActor(actor));
             app_state.print("Actor was set");
         }
         CliCommand::SetGasLimit {
             ref_time,
             proof_size,
         } => {
-            app_state.chain_info.gas_limit = Weight::from_parts(ref_time, proof_size);
+            let gas_limit = Weight::from_parts(ref_time, proof_size);
+            app_state.session.set_gas_limit(gas_limit);
+            app_state.chain_info.gas_limit = gas_limit;
+            app_state.history.push(HistoryEntry::SetGasLimit(gas_limit));
             app_state.print("Gas limit was set");
         }
 
@@ -60,6 +81,13 @@ pub fn execute(app_state: &mut AppState) -> Result<()> {
             salt,
         } => contract::deploy(app_state, constructor, args, salt),
         CliCommand::Call { message, args } => contract::call(app_state, message, args),
+
+        CliCommand::AssertReturn { expected } => assert_return(app_state, expected.join(" ")),
+        CliCommand::AssertFailure => assert_failure(app_state),
+
+        CliCommand::ExportTest { path, name } => export::export_test(app_state, path, name),
+
+        CliCommand::Messages => list_messages(app_state),
     }
 
     Ok(())
@@ -67,6 +95,7 @@ pub fn execute(app_state: &mut AppState) -> Result<()> {
 
 fn build_blocks(app_state: &mut AppState, count: u32) {
     app_state.chain_info.block_height = app_state.session.sandbox().build_blocks(count);
+    app_state.history.push(HistoryEntry::NextBlock(count));
     app_state.print(&format!("{count} blocks built"));
 }
 
@@ -76,6 +105,57 @@ fn add_tokens(app_state: &mut AppState, recipient: AccountId32, value: u128) ->
         .sandbox()
         .mint_into(&recipient, value)
         .map_err(|err| anyhow::format_err!("Failed to add token: {err:?}"))?;
+    app_state.history.push(HistoryEntry::AddTokens {
+        recipient: recipient.clone(),
+        value,
+    });
     app_state.print(&format!("{value} tokens added to {recipient}",));
     Ok(())
 }
+
+fn assert_return(app_state: &mut AppState, expected: String) {
+    let significant = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    match app_state.last_outcome.clone() {
+        Some(Ok(actual)) if significant(&actual) == significant(&expected) => {
+            app_state.print("Assertion passed")
+        }
+        Some(Ok(actual)) => app_state.print_error(&format!(
+            "Assertion failed: expected `{expected}`, got `{actual}`"
+        )),
+        Some(Err(err)) => app_state.print_error(&format!(
+            "Assertion failed: expected `{expected}`, but the last action failed\n{err}"
+        )),
+        None => app_state.print_error("Assertion failed: no deployment or call was made"),
+    }
+}
+
+fn assert_failure(app_state: &mut AppState) {
+    match app_state.last_outcome.clone() {
+        Some(Err(_)) => app_state.print("Assertion passed"),
+        Some(Ok(actual)) => app_state.print_error(&format!(
+            "Assertion failed: expected the last action to fail, got `{actual}`"
+        )),
+        None => app_state.print_error("Assertion failed: no deployment or call was made"),
+    }
+}
+
+fn list_messages(app_state: &mut AppState) {
+    let Some(contract) = app_state.contracts.current_contract() else {
+        app_state.print_error("No deployed contract");
+        return;
+    };
+
+    for message in messages(&contract.transcoder) {
+        let mut line = message.to_string();
+        if message.payable {
+            line.push_str(" [payable]");
+        }
+        if message.mutates {
+            line.push_str(" [mutates]");
+        }
+        app_state.print(&line);
+        for doc in message.docs.iter().filter(|doc| !doc.trim().is_empty()) {
+            app_state.print(&format!("    {}", doc.trim()));
+        }
+    }
+}
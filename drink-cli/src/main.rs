@@ -1,13 +1,19 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use drink::{
+    minimal::MinimalSandbox,
+    session::{scenario::Scenario, Session},
+};
 
-use crate::ui::run_ui;
+use crate::{batch::run_batch, rpc::serve, ui::run_ui};
 
 mod app_state;
+mod batch;
 mod cli;
 mod executor;
+mod rpc;
 mod ui;
 
 #[derive(Parser, Debug)]
@@ -16,9 +22,52 @@ struct Args {
     /// Starts the CLI in the provided directory
     #[arg(short, long, value_name = "DIRECTORY")]
     path: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Runs a scenario file against a fresh session and exits
+    Scenario {
+        /// Path to the scenario (TOML) file
+        file: PathBuf,
+    },
+    /// Executes CLI commands from a script (or the standard input) and exits
+    Run {
+        /// Path to the script with one command per line. If not given, commands are read from the
+        /// standard input
+        script: Option<PathBuf>,
+    },
+    /// Serves the sandbox over a subset of the Substrate JSON-RPC API (WebSocket)
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 9944)]
+        port: u16,
+        /// Session state file to start from (see `Session::save_state`)
+        #[arg(long)]
+        state: Option<PathBuf>,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    run_ui(args.path)
+    match args.command {
+        Some(Command::Scenario { file }) => run_scenario(file),
+        Some(Command::Run { script }) => run_batch(args.path, script),
+        Some(Command::Serve { port, state }) => serve(port, state),
+        None => run_ui(args.path),
+    }
+}
+
+fn run_scenario(file: PathBuf) -> Result<()> {
+    let scenario = Scenario::load(&file)?;
+    scenario.run(&mut Session::<MinimalSandbox>::default())?;
+    println!(
+        "Scenario {} passed ({} steps)",
+        file.display(),
+        scenario.steps.len()
+    );
+    Ok(())
 }
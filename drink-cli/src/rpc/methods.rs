@@ -0,0 +1,513 @@
+use drink::{
+    frame_support::{
+        sp_runtime::{
+            testing::H256,
+            traits::{BlakeTwo256, Block as BlockT, Hash},
+        },
+        storage::{unhashed, KeyPrefixIterator},
+    },
+    hex,
+    ink_sandbox::{frame_system, ContractExecResultFor, ContractInstantiateResultFor},
+    minimal::{Contracts, MinimalSandbox, MinimalSandboxRuntime, System},
+    pallet_contracts::{
+        Code, CollectEvents, ContractResult, DebugInfo, Determinism, StorageDeposit,
+    },
+    sandbox_api::prelude::*,
+    session::Session,
+    AccountId32, Sandbox, Ss58Codec, Weight,
+};
+use parity_scale_codec::{Decode, Encode};
+use serde_json::{json, Value};
+
+use crate::rpc::RpcError;
+
+type Extrinsic = <<MinimalSandboxRuntime as frame_system::Config>::Block as BlockT>::Extrinsic;
+
+/// Supported methods, as reported by `rpc_methods`.
+const METHODS: &[&str] = &[
+    "author_submitExtrinsic",
+    "chain_getBlockHash",
+    "chain_getFinalizedHead",
+    "chain_getHeader",
+    "contracts_call",
+    "contracts_instantiate",
+    "engine_createBlock",
+    "rpc_methods",
+    "state_call",
+    "state_getKeysPaged",
+    "state_getMetadata",
+    "state_getRuntimeVersion",
+    "state_getStorage",
+    "system_accountNextIndex",
+    "system_chain",
+    "system_chainType",
+    "system_health",
+    "system_name",
+    "system_properties",
+    "system_version",
+];
+
+/// Arguments of the `ContractsApi_call` runtime API.
+#[derive(Decode)]
+struct CallRequest {
+    origin: AccountId32,
+    dest: AccountId32,
+    value: u128,
+    gas_limit: Option<Weight>,
+    storage_deposit_limit: Option<u128>,
+    input_data: Vec<u8>,
+}
+
+/// Arguments of the `ContractsApi_instantiate` runtime API.
+#[derive(Decode)]
+struct InstantiateRequest {
+    origin: AccountId32,
+    value: u128,
+    gas_limit: Option<Weight>,
+    storage_deposit_limit: Option<u128>,
+    code: Code<H256>,
+    data: Vec<u8>,
+    salt: Vec<u8>,
+}
+
+/// Arguments of the `ContractsApi_upload_code` runtime API.
+#[derive(Decode)]
+struct UploadRequest {
+    origin: AccountId32,
+    code: Vec<u8>,
+    storage_deposit_limit: Option<u128>,
+    determinism: Determinism,
+}
+
+/// Handle a single RPC method call.
+pub fn call(
+    session: &mut Session<MinimalSandbox>,
+    method: &str,
+    params: &[Value],
+) -> Result<Value, RpcError> {
+    match method {
+        "rpc_methods" => Ok(json!({ "version": 1, "methods": METHODS })),
+        "system_name" | "system_chain" => Ok(json!("drink")),
+        "system_version" => Ok(json!(env!("CARGO_PKG_VERSION"))),
+        "system_chainType" => Ok(json!("Development")),
+        "system_properties" => Ok(json!({
+            "ss58Format": 42,
+            "tokenDecimals": 12,
+            "tokenSymbol": "UNIT",
+        })),
+        "system_health" => Ok(json!({
+            "peers": 0,
+            "isSyncing": false,
+            "shouldHavePeers": false,
+        })),
+        "system_accountNextIndex" => {
+            let account = parse_account(str_param(params, 0)?)?;
+            let nonce = session
+                .sandbox()
+                .execute_with(|| System::account_nonce(&account));
+            Ok(json!(nonce))
+        }
+
+        "chain_getBlockHash" => {
+            let number = match param(params, 0) {
+                Some(number) => number
+                    .as_u64()
+                    .and_then(|number| u32::try_from(number).ok())
+                    .ok_or_else(|| RpcError::invalid_params("Block number (u32) expected"))?,
+                None => head_number(session),
+            };
+            Ok(match number <= head_number(session) {
+                true => {
+                    block_hash(session, number).map_or(Value::Null, |hash| json!(hash_hex(hash)))
+                }
+                false => Value::Null,
+            })
+        }
+        "chain_getFinalizedHead" => {
+            let head = head_number(session);
+            Ok(json!(hash_hex(
+                block_hash(session, head).unwrap_or_default()
+            )))
+        }
+        "chain_getHeader" => header(session, params),
+
+        "state_getStorage" => {
+            let key = hex_param(params, 0)?;
+            let value = session.sandbox().execute_with(|| unhashed::get_raw(&key));
+            Ok(value.map_or(Value::Null, |value| json!(hex::encode(&value))))
+        }
+        "state_getKeysPaged" => {
+            let prefix = hex_param(params, 0)?;
+            let count = param(params, 1)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| RpcError::invalid_params("Key count expected"))?;
+            let start_key = match param(params, 2) {
+                None | Some(Value::Null) => None,
+                Some(_) => Some(hex_param(params, 2)?),
+            };
+            let keys = session
+                .sandbox()
+                .execute_with(|| storage_keys(prefix, start_key, count as usize));
+            Ok(json!(keys
+                .iter()
+                .map(|key| hex::encode(key))
+                .collect::<Vec<_>>()))
+        }
+        "state_getMetadata" => Ok(json!(hex::encode(&MinimalSandbox::get_metadata().encode()))),
+        // The sandbox runtime doesn't implement any runtime APIs - `state_call` emulates a few.
+        "state_getRuntimeVersion" => Ok(json!({
+            "specName": "drink",
+            "implName": "drink",
+            "authoringVersion": 0,
+            "specVersion": 1,
+            "implVersion": 0,
+            "apis": [],
+            "transactionVersion": 1,
+            "stateVersion": 1,
+        })),
+        "state_call" => state_call(session, params),
+
+        "contracts_call" => {
+            let request = param(params, 0)
+                .ok_or_else(|| RpcError::invalid_params("Call request expected"))?;
+            let request = CallRequest {
+                origin: parse_account(str_field(request, "origin")?)?,
+                dest: parse_account(str_field(request, "dest")?)?,
+                value: balance_field(request, "value")?.unwrap_or_default(),
+                gas_limit: weight_field(request, "gasLimit")?,
+                storage_deposit_limit: balance_field(request, "storageDepositLimit")?,
+                input_data: hex_field(request, "inputData")?,
+            };
+            let result = bare_call(session, request);
+            let output = match &result.result {
+                Ok(ret) => {
+                    json!({ "ok": { "flags": ret.flags.bits(), "data": hex::encode(&ret.data) } })
+                }
+                Err(err) => json!({ "err": format!("{err:?}") }),
+            };
+            Ok(contract_result_json(&result, output))
+        }
+        "contracts_instantiate" => {
+            let request = param(params, 0)
+                .ok_or_else(|| RpcError::invalid_params("Instantiate request expected"))?;
+            let code = match (
+                request["code"].get("upload"),
+                request["code"].get("existing"),
+            ) {
+                (Some(_), _) => Code::Upload(hex_field(&request["code"], "upload")?),
+                (None, Some(_)) => Code::Existing(H256::from_slice(&hash_bytes(hex_field(
+                    &request["code"],
+                    "existing",
+                )?)?)),
+                (None, None) => {
+                    return Err(RpcError::invalid_params(
+                        "`code` should be `{ upload }` or `{ existing }`",
+                    ))
+                }
+            };
+            let request = InstantiateRequest {
+                origin: parse_account(str_field(request, "origin")?)?,
+                value: balance_field(request, "value")?.unwrap_or_default(),
+                gas_limit: weight_field(request, "gasLimit")?,
+                storage_deposit_limit: balance_field(request, "storageDepositLimit")?,
+                code,
+                data: hex_field(request, "data")?,
+                salt: hex_field(request, "salt")?,
+            };
+            let result = bare_instantiate(session, request);
+            let output = match &result.result {
+                Ok(ret) => json!({ "ok": {
+                    "result": { "flags": ret.result.flags.bits(), "data": hex::encode(&ret.result.data) },
+                    "accountId": ret.account_id.to_string(),
+                } }),
+                Err(err) => json!({ "err": format!("{err:?}") }),
+            };
+            Ok(contract_result_json(&result, output))
+        }
+
+        "author_submitExtrinsic" => {
+            let encoded = hex_param(params, 0)?;
+            let extrinsic = Extrinsic::decode(&mut encoded.as_slice())
+                .map_err(|err| RpcError::invalid_params(format!("Invalid extrinsic: {err}")))?;
+            // Signatures are not verified: the call is dispatched on behalf of the signer. The
+            // nonce is not checked either, but it is incremented, as if the extrinsic was included.
+            let origin = extrinsic.signature.map(|(address, _, _)| address);
+            if let Some(signer) = &origin {
+                session
+                    .sandbox()
+                    .execute_with(|| System::inc_account_nonce(signer));
+            }
+            session
+                .sandbox()
+                .runtime_call(extrinsic.function, origin)
+                .map_err(|err| RpcError::server(format!("Dispatch failed: {:?}", err.error)))?;
+            Ok(json!(hash_hex(BlakeTwo256::hash(&encoded))))
+        }
+        "engine_createBlock" => {
+            session.sandbox().build_block();
+            let head = head_number(session);
+            Ok(json!({
+                "hash": hash_hex(block_hash(session, head).unwrap_or_default()),
+                "aux": { "header_modified": false },
+            }))
+        }
+
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
+
+/// Handle `state_call` for the contracts runtime API and metadata.
+fn state_call(session: &mut Session<MinimalSandbox>, params: &[Value]) -> Result<Value, RpcError> {
+    let method = str_param(params, 0)?;
+    let data = hex_param(params, 1)?;
+    let invalid = |err: parity_scale_codec::Error| {
+        RpcError::invalid_params(format!("Invalid arguments of {method}: {err}"))
+    };
+
+    let output = match method {
+        "ContractsApi_call" => bare_call(
+            session,
+            CallRequest::decode(&mut data.as_slice()).map_err(invalid)?,
+        )
+        .encode(),
+        "ContractsApi_instantiate" => bare_instantiate(
+            session,
+            InstantiateRequest::decode(&mut data.as_slice()).map_err(invalid)?,
+        )
+        .encode(),
+        "ContractsApi_upload_code" => {
+            let request = UploadRequest::decode(&mut data.as_slice()).map_err(invalid)?;
+            session
+                .sandbox()
+                .dry_run(|sandbox| {
+                    sandbox.execute_with(|| {
+                        Contracts::bare_upload_code(
+                            request.origin,
+                            request.code,
+                            request.storage_deposit_limit,
+                            request.determinism,
+                        )
+                    })
+                })
+                .encode()
+        }
+        // `OpaqueMetadata` is the encoded metadata wrapped in a `Vec<u8>`.
+        "Metadata_metadata" => MinimalSandbox::get_metadata().encode().encode(),
+        _ => {
+            return Err(RpcError::server(format!(
+                "Unsupported runtime API: {method}"
+            )))
+        }
+    };
+    Ok(json!(hex::encode(&output)))
+}
+
+/// Dry-run a contract call.
+fn bare_call(
+    session: &mut Session<MinimalSandbox>,
+    request: CallRequest,
+) -> ContractExecResultFor<MinimalSandboxRuntime> {
+    session.sandbox().dry_run(|sandbox| {
+        sandbox.execute_with(|| {
+            Contracts::bare_call(
+                request.origin,
+                request.dest,
+                request.value,
+                request
+                    .gas_limit
+                    .unwrap_or_else(MinimalSandbox::default_gas_limit),
+                request.storage_deposit_limit,
+                request.input_data,
+                DebugInfo::UnsafeDebug,
+                CollectEvents::UnsafeCollect,
+                Determinism::Enforced,
+            )
+        })
+    })
+}
+
+/// Dry-run a contract instantiation.
+fn bare_instantiate(
+    session: &mut Session<MinimalSandbox>,
+    request: InstantiateRequest,
+) -> ContractInstantiateResultFor<MinimalSandboxRuntime> {
+    session.sandbox().dry_run(|sandbox| {
+        sandbox.execute_with(|| {
+            Contracts::bare_instantiate(
+                request.origin,
+                request.value,
+                request
+                    .gas_limit
+                    .unwrap_or_else(MinimalSandbox::default_gas_limit),
+                request.storage_deposit_limit,
+                request.code,
+                request.data,
+                request.salt,
+                DebugInfo::UnsafeDebug,
+                CollectEvents::UnsafeCollect,
+            )
+        })
+    })
+}
+
+/// Up to `count` storage keys starting with `prefix`, in the lexicographic order. If `start_key` is
+/// given, only the keys after it are returned.
+fn storage_keys(prefix: Vec<u8>, start_key: Option<Vec<u8>>, count: usize) -> Vec<Vec<u8>> {
+    let (previous_key, include_prefix) = match start_key {
+        Some(start_key) if start_key >= prefix => (start_key, false),
+        _ => (prefix.clone(), unhashed::exists(&prefix)),
+    };
+    let keys = KeyPrefixIterator::new(prefix.clone(), previous_key, |suffix| Ok(suffix.to_vec()))
+        .map(|suffix| [prefix.as_slice(), &suffix].concat());
+    include_prefix
+        .then(|| prefix.clone())
+        .into_iter()
+        .chain(keys)
+        .take(count)
+        .collect()
+}
+
+/// The header of the block with the given hash (or of the last finalized block).
+fn header(session: &mut Session<MinimalSandbox>, params: &[Value]) -> Result<Value, RpcError> {
+    let head = head_number(session);
+    let number = match param(params, 0) {
+        None => head,
+        Some(_) => {
+            let hash = H256::from_slice(&hash_bytes(hex_param(params, 0)?)?);
+            (0..=head)
+                .rev()
+                .find(|number| block_hash(session, *number) == Some(hash))
+                .ok_or_else(|| RpcError::server("Unknown block"))?
+        }
+    };
+    let parent_hash = number
+        .checked_sub(1)
+        .and_then(|parent| block_hash(session, parent))
+        .unwrap_or_default();
+
+    // The sandbox doesn't compute storage and extrinsics roots.
+    Ok(json!({
+        "parentHash": hash_hex(parent_hash),
+        "number": format!("{number:#x}"),
+        "stateRoot": hash_hex(H256::zero()),
+        "extrinsicsRoot": hash_hex(H256::zero()),
+        "digest": { "logs": [] },
+    }))
+}
+
+/// The number of the last finalized block (the current block is still being built).
+fn head_number(session: &mut Session<MinimalSandbox>) -> u32 {
+    session.sandbox().block_number().saturating_sub(1)
+}
+
+/// The hash of a finalized block, if it is still known to the runtime.
+fn block_hash(session: &mut Session<MinimalSandbox>, number: u32) -> Option<H256> {
+    let hash = session
+        .sandbox()
+        .execute_with(|| System::block_hash(number));
+    (hash != H256::zero()).then_some(hash)
+}
+
+fn contract_result_json<R, E>(result: &ContractResult<R, u128, E>, output: Value) -> Value {
+    let weight =
+        |weight: Weight| json!({ "refTime": weight.ref_time(), "proofSize": weight.proof_size() });
+    // Balances are passed as strings, since they don't fit into JSON numbers.
+    let storage_deposit = match &result.storage_deposit {
+        StorageDeposit::Charge(amount) => json!({ "charge": amount.to_string() }),
+        StorageDeposit::Refund(amount) => json!({ "refund": amount.to_string() }),
+    };
+    json!({
+        "gasConsumed": weight(result.gas_consumed),
+        "gasRequired": weight(result.gas_required),
+        "storageDeposit": storage_deposit,
+        "debugMessage": String::from_utf8_lossy(&result.debug_message),
+        "result": output,
+    })
+}
+
+fn param(params: &[Value], index: usize) -> Option<&Value> {
+    params.get(index).filter(|param| !param.is_null())
+}
+
+fn str_param(params: &[Value], index: usize) -> Result<&str, RpcError> {
+    param(params, index)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params(format!("String expected at position {index}")))
+}
+
+fn hex_param(params: &[Value], index: usize) -> Result<Vec<u8>, RpcError> {
+    hex::decode(str_param(params, index)?)
+        .ok_or_else(|| RpcError::invalid_params(format!("Hex string expected at position {index}")))
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> Result<&'a str, RpcError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params(format!("Missing `{field}`")))
+}
+
+fn hex_field(value: &Value, field: &str) -> Result<Vec<u8>, RpcError> {
+    hex::decode(str_field(value, field)?)
+        .ok_or_else(|| RpcError::invalid_params(format!("`{field}` should be a hex string")))
+}
+
+/// A balance given as a number, or as a decimal or hex string. `None` if missing.
+fn balance_field(value: &Value, field: &str) -> Result<Option<u128>, RpcError> {
+    let invalid = || RpcError::invalid_params(format!("`{field}` should be a balance"));
+    match value.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .map(u128::from)
+            .map(Some)
+            .ok_or_else(invalid),
+        Some(Value::String(string)) => match string.strip_prefix("0x") {
+            Some(digits) => u128::from_str_radix(digits, 16),
+            None => string.parse(),
+        }
+        .map(Some)
+        .map_err(|_| invalid()),
+        Some(_) => Err(invalid()),
+    }
+}
+
+/// A weight given as `{ refTime, proofSize }`. `None` if missing.
+fn weight_field(value: &Value, field: &str) -> Result<Option<Weight>, RpcError> {
+    match value.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(weight) => match (
+            weight.get("refTime").and_then(Value::as_u64),
+            weight.get("proofSize").and_then(Value::as_u64),
+        ) {
+            (Some(ref_time), Some(proof_size)) => {
+                Ok(Some(Weight::from_parts(ref_time, proof_size)))
+            }
+            _ => Err(RpcError::invalid_params(format!(
+                "`{field}` should be `{{ refTime, proofSize }}`"
+            ))),
+        },
+    }
+}
+
+/// An account given as an SS58 address or a hex string.
+fn parse_account(account: &str) -> Result<AccountId32, RpcError> {
+    match hex::decode(account) {
+        Some(bytes) if account.starts_with("0x") => <[u8; 32]>::try_from(bytes)
+            .map(AccountId32::new)
+            .map_err(|_| RpcError::invalid_params("Account should have 32 bytes")),
+        _ => AccountId32::from_ss58check(account)
+            .map_err(|err| RpcError::invalid_params(format!("Invalid account {account}: {err:?}"))),
+    }
+}
+
+fn hash_bytes(bytes: Vec<u8>) -> Result<Vec<u8>, RpcError> {
+    match bytes.len() {
+        32 => Ok(bytes),
+        _ => Err(RpcError::invalid_params("Hash should have 32 bytes")),
+    }
+}
+
+fn hash_hex(hash: H256) -> String {
+    hex::encode(hash.as_bytes())
+}
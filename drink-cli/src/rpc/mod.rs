@@ -0,0 +1,475 @@
+//! Local node emulation: the sandbox exposed over a subset of the Substrate JSON-RPC API.
+//!
+//! The server is single-threaded: connections are polled in turn and requests are handled one by
+//! one against the same session.
+//!
+//! The emulation is meant for clients that query the state, dry-run contracts and submit extrinsics
+//! without watching them. It is not a full node: subscriptions are out of scope, extrinsic
+//! signatures and nonces are not checked, no runtime APIs are reported in the runtime version and
+//! block headers have zero state and extrinsics roots.
+
+mod methods;
+
+use std::{
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use drink::{minimal::MinimalSandbox, session::Session};
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+/// How long to sleep when there is nothing to do.
+const IDLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// JSON-RPC error: a code and a message.
+pub struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: -32602,
+            message: message.into(),
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self {
+            code: -32601,
+            message: format!("Method not found: {method}"),
+        }
+    }
+
+    pub fn server(message: impl Into<String>) -> Self {
+        Self {
+            code: -32000,
+            message: message.into(),
+        }
+    }
+}
+
+/// Serve the JSON-RPC API over WebSocket on `127.0.0.1:{port}`. If `state` is given, the session
+/// state is loaded from it first (see `Session::load_state`).
+pub fn serve(port: u16, state: Option<PathBuf>) -> Result<()> {
+    let mut session = Session::<MinimalSandbox>::default();
+    if let Some(state) = state {
+        session.load_state(state)?;
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    println!("Listening on ws://127.0.0.1:{port}");
+
+    let mut connections: Vec<WebSocket<TcpStream>> = vec![];
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => match accept(stream) {
+                Ok(connection) => connections.push(connection),
+                Err(err) => eprintln!("Failed to accept connection: {err}"),
+            },
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut idle = true;
+        connections.retain_mut(|connection| {
+            // Send out anything that didn't fit into the socket before.
+            if !is_alive(connection.flush()) {
+                return false;
+            }
+            match connection.read() {
+                Ok(Message::Text(request)) => {
+                    idle = false;
+                    let response = handle_request(&mut session, &request);
+                    is_alive(connection.send(Message::text(response)))
+                }
+                Ok(Message::Close(_)) => false,
+                Ok(_) => true,
+                Err(err) => is_alive(Err(err)),
+            }
+        });
+
+        if idle {
+            thread::sleep(IDLE_INTERVAL);
+        }
+    }
+}
+
+/// Perform the WebSocket handshake (in the blocking mode) and switch the connection to the
+/// non-blocking mode.
+fn accept(stream: TcpStream) -> Result<WebSocket<TcpStream>> {
+    stream.set_nonblocking(false)?;
+    let connection = tungstenite::accept(stream)?;
+    connection.get_ref().set_nonblocking(true)?;
+    Ok(connection)
+}
+
+/// Whether the connection can still be used after an operation with `result`.
+fn is_alive(result: tungstenite::Result<()>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(tungstenite::Error::Io(err)) => err.kind() == ErrorKind::WouldBlock,
+        Err(_) => false,
+    }
+}
+
+/// Handle a single request or a batch of requests.
+fn handle_request(session: &mut Session<MinimalSandbox>, request: &str) -> String {
+    let response = match serde_json::from_str::<Value>(request) {
+        Ok(Value::Array(batch)) => Value::Array(
+            batch
+                .iter()
+                .map(|request| handle_call(session, request))
+                .collect(),
+        ),
+        Ok(request) => handle_call(session, &request),
+        Err(err) => error_response(
+            Value::Null,
+            RpcError {
+                code: -32700,
+                message: format!("Parse error: {err}"),
+            },
+        ),
+    };
+    response.to_string()
+}
+
+fn handle_call(session: &mut Session<MinimalSandbox>, request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return error_response(
+            id,
+            RpcError {
+                code: -32600,
+                message: "Invalid request".to_string(),
+            },
+        );
+    };
+    let params = match request.get("params") {
+        Some(Value::Array(params)) => params.clone(),
+        Some(Value::Null) | None => vec![],
+        Some(param) => vec![param.clone()],
+    };
+
+    match methods::call(session, method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => error_response(id, err),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use drink::{
+        frame_support::{sp_runtime::generic::UncheckedExtrinsic, storage::StoragePrefixedMap},
+        hex,
+        ink_sandbox::{frame_system, ContractExecResultFor},
+        minimal::{Contracts, MinimalSandboxRuntime, RuntimeCall, RuntimeEvent},
+        sandbox_api::prelude::*,
+        AccountId32, Sandbox, Ss58Codec, Weight,
+    };
+    use parity_scale_codec::{Decode, Encode};
+
+    use super::*;
+
+    /// Returns its input.
+    const ECHO: &str = r#"
+(module
+    (import "seal0" "seal_input" (func $seal_input (param i32 i32)))
+    (import "seal0" "seal_return" (func $seal_return (param i32 i32 i32)))
+    (import "env" "memory" (memory 1 1))
+
+    ;; [0, 4) input buffer size, [4, 260) input buffer
+    (data (i32.const 0) "\00\01")
+
+    (func (export "deploy"))
+
+    (func (export "call")
+        (call $seal_input (i32.const 4) (i32.const 0))
+        (call $seal_return (i32.const 0) (i32.const 4) (i32.load (i32.const 0)))
+    )
+)"#;
+
+    fn request(session: &mut Session<MinimalSandbox>, request: &str) -> Value {
+        serde_json::from_str(&handle_request(session, request)).unwrap()
+    }
+
+    fn call(session: &mut Session<MinimalSandbox>, method: &str, params: Value) -> Value {
+        request(
+            session,
+            &json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string(),
+        )
+    }
+
+    fn actor() -> AccountId32 {
+        MinimalSandbox::default_actor()
+    }
+
+    fn deploy_echo(session: &mut Session<MinimalSandbox>) -> AccountId32 {
+        session
+            .sandbox()
+            .deploy_contract(
+                wat::parse_str(ECHO).unwrap(),
+                0,
+                vec![],
+                vec![],
+                actor(),
+                MinimalSandbox::default_gas_limit(),
+                None,
+            )
+            .result
+            .expect("Deployment should succeed")
+            .account_id
+    }
+
+    #[test]
+    fn single_request_is_handled() {
+        let response = request(
+            &mut Session::default(),
+            r#"{"jsonrpc": "2.0", "id": 7, "method": "system_name"}"#,
+        );
+
+        assert_eq!(
+            response,
+            json!({ "jsonrpc": "2.0", "id": 7, "result": "drink" })
+        );
+    }
+
+    #[test]
+    fn batch_is_handled_in_order() {
+        let response = request(
+            &mut Session::default(),
+            r#"[
+                {"jsonrpc": "2.0", "id": 1, "method": "system_chainType"},
+                {"jsonrpc": "2.0", "id": 2, "method": "unknown_method"},
+                {"jsonrpc": "2.0", "id": 3}
+            ]"#,
+        );
+
+        assert_eq!(
+            response,
+            json!([
+                { "jsonrpc": "2.0", "id": 1, "result": "Development" },
+                {
+                    "jsonrpc": "2.0",
+                    "id": 2,
+                    "error": { "code": -32601, "message": "Method not found: unknown_method" },
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "id": 3,
+                    "error": { "code": -32600, "message": "Invalid request" },
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_error_is_reported() {
+        let response = request(&mut Session::default(), r#"{"jsonrpc": "2.0","#);
+
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], -32700);
+    }
+
+    #[test]
+    fn block_number_overflow_is_rejected() {
+        let response = request(
+            &mut Session::default(),
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "chain_getBlockHash", "params": [4294967296]}"#,
+        );
+
+        assert_eq!(response["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn block_hashes_are_known_for_built_blocks() {
+        let mut session = Session::default();
+        let hash = |session: &mut Session<MinimalSandbox>, number: u32| {
+            request(
+                session,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "chain_getBlockHash",
+                    "params": [number],
+                })
+                .to_string(),
+            )["result"]
+                .clone()
+        };
+
+        assert_eq!(hash(&mut session, 1), Value::Null);
+        request(
+            &mut session,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "engine_createBlock"}"#,
+        );
+        assert!(hash(&mut session, 1).is_string());
+    }
+
+    #[test]
+    fn malformed_hex_params_are_rejected() {
+        let response = request(
+            &mut Session::default(),
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "state_getStorage", "params": ["0x€a"]}"#,
+        );
+
+        assert_eq!(response["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn contracts_call_is_dry_run() {
+        let mut session = Session::default();
+        let contract = deploy_echo(&mut session);
+
+        let response = call(
+            &mut session,
+            "contracts_call",
+            json!([{
+                "origin": actor().to_string(),
+                "dest": contract.to_string(),
+                "value": 0,
+                "inputData": "0x01020304",
+            }]),
+        );
+
+        assert_eq!(
+            response["result"]["result"],
+            json!({ "ok": { "flags": 0, "data": "0x01020304" } })
+        );
+        assert!(response["result"]["gasConsumed"]["refTime"].as_u64() > Some(0));
+    }
+
+    #[test]
+    fn contracts_instantiate_does_not_deploy() {
+        let mut session = Session::default();
+
+        let response = call(
+            &mut session,
+            "contracts_instantiate",
+            json!([{
+                "origin": actor().to_string(),
+                "code": { "upload": hex::encode(&wat::parse_str(ECHO).unwrap()) },
+                "data": "0x",
+                "salt": "0x",
+            }]),
+        );
+
+        let address = response["result"]["result"]["ok"]["accountId"]
+            .as_str()
+            .expect("Instantiation should succeed");
+        let address = AccountId32::from_ss58check(address).unwrap();
+        assert!(session
+            .sandbox()
+            .execute_with(|| Contracts::code_hash(&address))
+            .is_none());
+    }
+
+    #[test]
+    fn state_call_emulates_contracts_api() {
+        let mut session = Session::default();
+        let contract = deploy_echo(&mut session);
+        let arguments = (
+            actor(),
+            contract,
+            0u128,
+            None::<Weight>,
+            None::<u128>,
+            vec![7u8, 8],
+        );
+
+        let response = call(
+            &mut session,
+            "state_call",
+            json!(["ContractsApi_call", hex::encode(&arguments.encode())]),
+        );
+
+        let output = hex::decode(response["result"].as_str().unwrap()).unwrap();
+        let result =
+            ContractExecResultFor::<MinimalSandboxRuntime>::decode(&mut output.as_slice()).unwrap();
+        assert_eq!(result.result.unwrap().data, vec![7, 8]);
+
+        let response = call(
+            &mut session,
+            "state_call",
+            json!(["ContractsApi_unknown", "0x"]),
+        );
+        assert_eq!(response["error"]["code"], -32000);
+    }
+
+    #[test]
+    fn submitted_extrinsic_is_dispatched_and_increments_nonce() {
+        let mut session = Session::default();
+        let remark = RuntimeCall::System(frame_system::Call::remark_with_event {
+            remark: b"drink".to_vec(),
+        });
+        let extrinsic = UncheckedExtrinsic::<AccountId32, RuntimeCall, (), ()>::new_signed(
+            remark,
+            actor(),
+            (),
+            (),
+        );
+        let nonce = |session: &mut Session<MinimalSandbox>| {
+            call(
+                session,
+                "system_accountNextIndex",
+                json!([actor().to_string()]),
+            )["result"]
+                .clone()
+        };
+
+        assert_eq!(nonce(&mut session), json!(0));
+        let response = call(
+            &mut session,
+            "author_submitExtrinsic",
+            json!([hex::encode(&extrinsic.encode())]),
+        );
+
+        assert!(response["result"].is_string());
+        assert_eq!(nonce(&mut session), json!(1));
+        assert!(session.sandbox().events().iter().any(|record| matches!(
+            record.event,
+            RuntimeEvent::System(frame_system::Event::Remarked { .. })
+        )));
+    }
+
+    #[test]
+    fn storage_keys_are_paged() {
+        let mut session = Session::<MinimalSandbox>::default();
+        let keys = |session: &mut Session<MinimalSandbox>, params: Value| {
+            call(session, "state_getKeysPaged", params)["result"]
+                .as_array()
+                .expect("Keys expected")
+                .clone()
+        };
+        session
+            .sandbox()
+            .mint_into(&AccountId32::new([7; 32]), 1_000_000_000_000)
+            .unwrap();
+        let prefix = hex::encode(&frame_system::Account::<MinimalSandboxRuntime>::final_prefix());
+        let prefix = prefix.as_str();
+
+        let all = keys(&mut session, json!([prefix, 100]));
+        assert!(all.len() > 1);
+        assert!(all
+            .iter()
+            .all(|key| key.as_str().unwrap().starts_with(prefix)));
+
+        let page = keys(&mut session, json!([prefix, 1, all[0]]));
+        assert_eq!(page, vec![all[1].clone()]);
+    }
+}
@@ -35,6 +35,26 @@ pub(super) fn build(_app_state: &AppState) -> impl Widget {
             "set-gas-limit <ref_time> <proof_size>",
             "set gas limits to <ref_time> and <proof_size>",
         ),
+        command(
+            "assert-return <value>",
+            "check that the last call returned <value> (e.g. `Ok(true)`)",
+        ),
+        command(
+            "assert-failure",
+            "check that the last deployment or call failed",
+        ),
+        command(
+            "messages / m",
+            "list messages of the current contract with their signatures and docs",
+        ),
+        command(
+            "<Tab>",
+            "complete command, message or constructor name (switch contract if the input is empty)",
+        ),
+        command(
+            "export-test <file> [--name <name>]",
+            "save the session history as a `#[drink::test]` function in <file>",
+        ),
     ])
     .block(section("Help"))
 }
@@ -79,6 +79,11 @@ fn run_ui_app(terminal: &mut Terminal, cwd_override: Option<PathBuf>) -> Result<
                 (Drinking, KeyCode::Backspace) => {
                     app_state.ui_state.user_input.pop();
                 }
+                (Drinking, KeyCode::Tab)
+                    if !app_state.ui_state.user_input.current_input().is_empty() =>
+                {
+                    app_state.complete_input()
+                }
                 (Drinking, KeyCode::Tab) => {
                     let prev_path = match app_state.contracts.current_contract() {
                         Some(c) => c.base_path.clone(),
@@ -1,5 +1,6 @@
 use ratatui::{
     style::{Color, Style},
+    text::{Line, Span},
     widgets::{Paragraph, Widget},
 };
 
@@ -14,6 +15,18 @@ pub(super) fn build(app_state: &mut AppState) -> impl Widget {
         style = style.fg(Color::DarkGray);
     }
 
+    let input = app_state.ui_state.user_input.current_input().to_string();
+    let mut line = vec![Span::raw(input.clone())];
+    if app_state.ui_state.mode == Mode::Drinking {
+        if let Some(hint) = app_state.input_hint() {
+            let separator = if input.ends_with(' ') { "" } else { " " };
+            line.push(Span::styled(
+                format!("{separator}{hint}"),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+    }
+
     let block = section("User input").style(style);
-    Paragraph::new(app_state.ui_state.user_input.current_input().to_string()).block(block)
+    Paragraph::new(Line::from(line)).block(block)
 }
@@ -0,0 +1,164 @@
+//! This module provides a generic chain extension, whose functions can be defined at test time,
+//! without a need of implementing `ChainExtension` and creating a dedicated sandbox runtime.
+//!
+//! The flow is analogous to the one of `pallet_contracts_debugging`:
+//! `DrinkChainExtension` (runtime side) -> runtime interface -> `ChainExtensionExt` (user side).
+//! `Session` registers its own `ChainExtensionRegistry` as the extension, so handlers can be simply
+//! added with `session.chain_extension().on(func_id, |input| ...)`.
+//!
+//! Every handled call is additionally reported to the tracing extension (see
+//! `TracingExtT::after_chain_extension_call`), so that the session can record it.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime_interface::runtime_interface;
+
+use crate::{
+    pallet_contracts::chain_extension::{
+        ChainExtension, Config, Environment, Ext, InitState, Result as ExtensionResult, RetVal,
+    },
+    pallet_contracts_debugging::contract_call_debugger,
+    sp_externalities::{decl_extension, ExternalitiesExt},
+    DispatchError,
+};
+
+/// Chain extension that forwards all the calls to the `ChainExtensionExt` runtime extension.
+///
+/// If there is no handler for the called function or the handler fails, the call fails (and thus
+/// the calling contract traps).
+#[derive(Default)]
+pub struct DrinkChainExtension;
+
+impl<R: Config> ChainExtension<R> for DrinkChainExtension {
+    fn call<E: Ext<T = R>>(
+        &mut self,
+        mut env: Environment<E, InitState>,
+    ) -> ExtensionResult<RetVal> {
+        let (ext_id, func_id) = (env.ext_id(), env.func_id());
+        let contract_address = env.ext().address().encode();
+        let mut env = env.buf_in_buf_out();
+        let input = env.read(env.in_len())?;
+
+        let encoded = chain_extension_forwarder::call(ext_id, func_id, input.clone())
+            .ok_or(DispatchError::Other("Unhandled chain extension function"))?;
+        let result: Result<(u32, Vec<u8>), DispatchError> = Decode::decode(&mut encoded.as_slice())
+            .map_err(|_| DispatchError::Other("Chain extension output is not decodable"))?;
+        let (ret_code, output) = result?;
+
+        env.write(&output, false, None)?;
+        contract_call_debugger::after_chain_extension_call(
+            contract_address,
+            ext_id,
+            func_id,
+            input,
+            output,
+            ret_code,
+        );
+        Ok(RetVal::Converging(ret_code))
+    }
+}
+
+/// Runtime interface through which `DrinkChainExtension` reaches the `ChainExtensionExt`.
+#[runtime_interface]
+pub trait ChainExtensionForwarder {
+    /// Returns the encoded outcome of the chain extension function registered in the session, or
+    /// `None` if there is no such function.
+    fn call(&mut self, ext_id: u16, func_id: u16, input: Vec<u8>) -> Option<Vec<u8>> {
+        self.extension::<ChainExtensionExt>()
+            .and_then(|ext| ext.call(ext_id, func_id, input))
+    }
+}
+
+/// This trait describes a runtime extension that handles chain extension calls.
+pub trait ChainExtensionExtT {
+    /// Called when a contract calls function `func_id` of the chain extension `ext_id`.
+    ///
+    /// Returns `None` if the function is not supported. Otherwise, returns a valid codec encoding
+    /// of `Result<(u32, Vec<u8>), DispatchError>`: either the return code and the output passed
+    /// back to the contract, or the error with which the call fails.
+    fn call(&self, ext_id: u16, func_id: u16, input: Vec<u8>) -> Option<Vec<u8>>;
+}
+
+decl_extension! {
+    /// A wrapper type for the `ChainExtensionExtT` extension.
+    pub struct ChainExtensionExt(Box<dyn ChainExtensionExtT + Send>);
+}
+
+/// A handler of a single chain extension function. Receives the input passed by the contract and
+/// returns the return code and the output. If it returns an error, the call fails.
+pub type ChainExtensionHandler =
+    Box<dyn FnMut(Vec<u8>) -> Result<(u32, Vec<u8>), DispatchError> + Send>;
+
+/// A collection of chain extension function handlers.
+///
+/// The registry is cheaply cloneable - all the clones share the same set of handlers, so that new
+/// handlers can be added after the registry has been moved into the sandbox.
+#[derive(Clone, Default)]
+pub struct ChainExtensionRegistry {
+    handlers: Arc<Mutex<BTreeMap<(u16, u16), ChainExtensionHandler>>>,
+}
+
+impl ChainExtensionRegistry {
+    /// Creates a new, empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles function `func_id` of the default chain extension (with id `0`) with `handler`,
+    /// which receives the encoded input and returns the encoded output. The return code is `0`.
+    pub fn on<H: FnMut(Vec<u8>) -> Vec<u8> + Send + 'static>(&self, func_id: u16, mut handler: H) {
+        self.on_extension(0, func_id, Box::new(move |input| Ok((0, handler(input)))));
+    }
+
+    /// Handles function `func_id` of the default chain extension (with id `0`) with a typed
+    /// `handler`. Takes care of decoding the input and encoding the output. If the input cannot be
+    /// decoded, the call fails.
+    pub fn on_typed<Args: Decode, Ret: Encode, H: FnMut(Args) -> Ret + Send + 'static>(
+        &self,
+        func_id: u16,
+        mut handler: H,
+    ) {
+        self.on_extension(
+            0,
+            func_id,
+            Box::new(move |input| {
+                let args = Decode::decode(&mut input.as_slice())
+                    .map_err(|_| DispatchError::Other("Chain extension input is not decodable"))?;
+                Ok((0, handler(args).encode()))
+            }),
+        );
+    }
+
+    /// Handles function `func_id` of the chain extension `ext_id` with `handler`. Replaces the
+    /// previous handler, if any.
+    pub fn on_extension(&self, ext_id: u16, func_id: u16, handler: ChainExtensionHandler) {
+        self.handlers
+            .lock()
+            .expect("Should be able to acquire chain extension registry")
+            .insert((ext_id, func_id), handler);
+    }
+
+    /// Removes the handler of function `func_id` of the chain extension `ext_id`. Returns `true`
+    /// if there was one.
+    pub fn remove(&self, ext_id: u16, func_id: u16) -> bool {
+        self.handlers
+            .lock()
+            .expect("Should be able to acquire chain extension registry")
+            .remove(&(ext_id, func_id))
+            .is_some()
+    }
+}
+
+impl ChainExtensionExtT for ChainExtensionRegistry {
+    fn call(&self, ext_id: u16, func_id: u16, input: Vec<u8>) -> Option<Vec<u8>> {
+        self.handlers
+            .lock()
+            .expect("Should be able to acquire chain extension registry")
+            .get_mut(&(ext_id, func_id))
+            .map(|handler| handler(input).encode())
+    }
+}
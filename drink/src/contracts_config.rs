@@ -0,0 +1,480 @@
+//! Runtime-configurable parameters of the contracts pallet.
+//!
+//! Runtimes created with `drink::create_sandbox!` don't hardcode the limits and deposits of the
+//! contracts pallet. Instead, they read them from `ContractsConfig`, which is kept in the sandbox
+//! storage under a well-known key. This way, tests can emulate the exact parameters of their target
+//! chain without defining a dedicated runtime:
+//!
+//! ```rust, ignore
+//! let config = ContractsConfig::default()
+//!     .with_max_code_len(64 * 1024)
+//!     .with_deposit_per_byte(10);
+//! let session = Session::<MinimalSandbox>::default().with_contracts_config(config)?;
+//! ```
+//!
+//! Note: the maximal call depth is determined by the `CallStack` type and thus cannot be changed at
+//! runtime. Instead, it is a parameter of `drink::create_sandbox!` (`max_call_depth = N`, 5 by
+//! default).
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    mem::size_of,
+};
+
+use parity_scale_codec::{Compact, Decode, Encode};
+
+use crate::{
+    frame_support::{
+        sp_runtime::Perbill,
+        storage::{storage_prefix, unhashed, PrefixIterator},
+        traits::{Get, PalletInfoAccess},
+    },
+    pallet_contracts::{Config, Frame, Limits, Pallet, Schedule},
+    sp_externalities, DrinkResult, Error, Sandbox,
+};
+
+/// The storage key under which the encoded `ContractsConfig` is kept.
+pub const CONTRACTS_CONFIG_KEY: &[u8] = b":drink:contracts_config";
+
+/// Parameters of the contracts pallet that can be changed at runtime.
+///
+/// The default values are the ones that `drink::create_sandbox!` runtimes have always used.
+/// Balances are expressed as `u128`, which is the balance type of these runtimes.
+#[derive(Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ContractsConfig {
+    /// Limits of the contract execution (memory pages, event topics, payload length etc.).
+    pub limits: Limits,
+    /// The maximal length of a contract code in bytes.
+    pub max_code_len: u32,
+    /// The maximal length of a storage key in bytes.
+    pub max_storage_key_len: u32,
+    /// The maximal size of the debug buffer in bytes.
+    pub max_debug_buffer_len: u32,
+    /// The deposit charged for every byte of contract storage.
+    pub deposit_per_byte: u128,
+    /// The deposit charged for every item of contract storage.
+    pub deposit_per_item: u128,
+    /// The storage deposit limit used when the caller doesn't specify one.
+    pub default_deposit_limit: u128,
+    /// The part of the code deposit that is locked when a contract sets its code hash.
+    pub code_hash_lockup_deposit_percent: Perbill,
+    /// The maximal number of delegate dependencies of a contract.
+    pub max_delegate_dependencies: u32,
+    /// Whether contracts can use unstable host functions.
+    pub unsafe_unstable_interface: bool,
+}
+
+// `Limits` implements `Debug` only with the `runtime-benchmarks` feature of the contracts pallet.
+impl Debug for ContractsConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let limits = &self.limits;
+        f.debug_struct("ContractsConfig")
+            .field(
+                "limits",
+                &format_args!(
+                    "Limits {{ event_topics: {}, memory_pages: {}, subject_len: {}, \
+                     payload_len: {}, runtime_memory: {} }}",
+                    limits.event_topics,
+                    limits.memory_pages,
+                    limits.subject_len,
+                    limits.payload_len,
+                    limits.runtime_memory
+                ),
+            )
+            .field("max_code_len", &self.max_code_len)
+            .field("max_storage_key_len", &self.max_storage_key_len)
+            .field("max_debug_buffer_len", &self.max_debug_buffer_len)
+            .field("deposit_per_byte", &self.deposit_per_byte)
+            .field("deposit_per_item", &self.deposit_per_item)
+            .field("default_deposit_limit", &self.default_deposit_limit)
+            .field(
+                "code_hash_lockup_deposit_percent",
+                &self.code_hash_lockup_deposit_percent,
+            )
+            .field("max_delegate_dependencies", &self.max_delegate_dependencies)
+            .field("unsafe_unstable_interface", &self.unsafe_unstable_interface)
+            .finish()
+    }
+}
+
+impl Default for ContractsConfig {
+    fn default() -> Self {
+        Self {
+            limits: Limits::default(),
+            max_code_len: 123 * 1024,
+            max_storage_key_len: 128,
+            max_debug_buffer_len: 2 * 1024 * 1024,
+            deposit_per_byte: 1,
+            deposit_per_item: 1,
+            default_deposit_limit: 10_000_000,
+            code_hash_lockup_deposit_percent: Perbill::from_percent(0),
+            max_delegate_dependencies: 32,
+            unsafe_unstable_interface: false,
+        }
+    }
+}
+
+impl ContractsConfig {
+    /// Sets the limits of the contract execution.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Sets the maximal length of a contract code.
+    pub fn with_max_code_len(self, max_code_len: u32) -> Self {
+        Self {
+            max_code_len,
+            ..self
+        }
+    }
+
+    /// Sets the maximal length of a storage key.
+    pub fn with_max_storage_key_len(self, max_storage_key_len: u32) -> Self {
+        Self {
+            max_storage_key_len,
+            ..self
+        }
+    }
+
+    /// Sets the maximal size of the debug buffer.
+    pub fn with_max_debug_buffer_len(self, max_debug_buffer_len: u32) -> Self {
+        Self {
+            max_debug_buffer_len,
+            ..self
+        }
+    }
+
+    /// Sets the deposit charged for every byte of contract storage.
+    pub fn with_deposit_per_byte(self, deposit_per_byte: u128) -> Self {
+        Self {
+            deposit_per_byte,
+            ..self
+        }
+    }
+
+    /// Sets the deposit charged for every item of contract storage.
+    pub fn with_deposit_per_item(self, deposit_per_item: u128) -> Self {
+        Self {
+            deposit_per_item,
+            ..self
+        }
+    }
+
+    /// Sets the storage deposit limit used when the caller doesn't specify one.
+    pub fn with_default_deposit_limit(self, default_deposit_limit: u128) -> Self {
+        Self {
+            default_deposit_limit,
+            ..self
+        }
+    }
+
+    /// Sets the part of the code deposit that is locked when a contract sets its code hash.
+    pub fn with_code_hash_lockup_deposit_percent(
+        self,
+        code_hash_lockup_deposit_percent: Perbill,
+    ) -> Self {
+        Self {
+            code_hash_lockup_deposit_percent,
+            ..self
+        }
+    }
+
+    /// Sets the maximal number of delegate dependencies of a contract.
+    pub fn with_max_delegate_dependencies(self, max_delegate_dependencies: u32) -> Self {
+        Self {
+            max_delegate_dependencies,
+            ..self
+        }
+    }
+
+    /// Allows (or forbids) contracts to use unstable host functions.
+    pub fn with_unsafe_unstable_interface(self, unsafe_unstable_interface: bool) -> Self {
+        Self {
+            unsafe_unstable_interface,
+            ..self
+        }
+    }
+
+    /// Checks that the configuration is usable with the runtime `R`, i.e. that there are no zero
+    /// limits and that the limits are consistent with each other. These are the same conditions
+    /// that the contracts pallet checks in its integrity test.
+    pub fn validate<R: Config>(&self) -> DrinkResult<()> {
+        let invalid = |reason: String| Err(Error::InvalidContractsConfig(reason));
+
+        for (name, value) in [
+            ("max_code_len", self.max_code_len),
+            ("max_storage_key_len", self.max_storage_key_len),
+            ("limits.memory_pages", self.limits.memory_pages),
+            ("limits.payload_len", self.limits.payload_len),
+            ("limits.runtime_memory", self.limits.runtime_memory),
+        ] {
+            if value == 0 {
+                return invalid(format!("`{name}` must not be zero"));
+            }
+        }
+
+        if self.max_debug_buffer_len <= MIN_DEBUG_BUFFER_LEN {
+            return invalid(format!(
+                "`max_debug_buffer_len` must be greater than {MIN_DEBUG_BUFFER_LEN}"
+            ));
+        }
+
+        let code_len_limit = self.code_len_limit(max_call_depth::<R>());
+        if self.max_code_len >= code_len_limit {
+            return invalid(format!(
+                "`max_code_len` must be less than {code_len_limit} for the given `limits` \
+                 (`runtime_memory`, `memory_pages`) and the call depth of the runtime"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The upper bound of `max_code_len`, as computed by the contracts pallet: every frame on the
+    /// call stack must fit in a half of the runtime memory, together with its heap and stack.
+    fn code_len_limit(&self, max_call_depth: u32) -> u32 {
+        const MAX_STACK_SIZE: u32 = 1024 * 1024;
+        const BYTES_PER_CODE_BYTE: u32 = 17 * 4;
+
+        let max_heap_size = self.limits.memory_pages.saturating_mul(64 * 1024);
+        (self.limits.runtime_memory / 2)
+            .saturating_div(max_call_depth)
+            .saturating_sub(max_heap_size)
+            .saturating_sub(MAX_STACK_SIZE)
+            / BYTES_PER_CODE_BYTE
+    }
+
+    /// Returns the configuration currently stored in the sandbox. Must be called within the sandbox
+    /// externalities.
+    ///
+    /// Outside the externalities (e.g. when the runtime metadata is being built), the default
+    /// configuration is returned.
+    pub fn current() -> Self {
+        sp_externalities::with_externalities(|ext| ext.storage(CONTRACTS_CONFIG_KEY))
+            .flatten()
+            .map(|encoded| {
+                Decode::decode(&mut encoded.as_slice())
+                    .expect("Contracts config should be decodable")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Stores the configuration in `sandbox`. It affects all the subsequent contract interactions.
+    ///
+    /// Fails if `max_code_len` is less than the length of a code already stored in the sandbox,
+    /// since the contracts pallet couldn't read such code (nor call its contracts) anymore.
+    pub fn apply<S: Sandbox>(&self, sandbox: &mut S) -> DrinkResult<()>
+    where
+        S::Runtime: Config,
+    {
+        sandbox.execute_with(|| {
+            let longest_code = longest_stored_code::<S::Runtime>();
+            if self.max_code_len < longest_code {
+                return Err(Error::InvalidContractsConfig(format!(
+                    "`max_code_len` must not be less than {longest_code}, the length of a stored \
+                     code"
+                )));
+            }
+            unhashed::put(CONTRACTS_CONFIG_KEY, self);
+            Ok(())
+        })
+    }
+}
+
+/// The length of the longest code stored by the contracts pallet of `R` (0 if there is none).
+/// Must be called within the sandbox externalities.
+fn longest_stored_code<R: Config>() -> u32 {
+    // `PristineCode` is private to the pallet, so its (length-prefixed) values are read directly.
+    let prefix = storage_prefix(Pallet::<R>::name().as_bytes(), b"PristineCode").to_vec();
+    PrefixIterator::<u32>::new(prefix.clone(), prefix, |_, mut code| {
+        Compact::<u32>::decode(&mut code).map(|len| len.0)
+    })
+    .max()
+    .unwrap_or_default()
+}
+
+/// The minimal size of the debug buffer required by the contracts pallet.
+const MIN_DEBUG_BUFFER_LEN: u32 = 256;
+
+/// The maximal call depth of the runtime `R`, including the top-level frame.
+fn max_call_depth<R: Config>() -> u32 {
+    let call_stack_size = size_of::<R::CallStack>() / size_of::<Frame<R>>();
+    (call_stack_size + 1) as u32
+}
+
+macro_rules! config_getter {
+    ($(#[$doc:meta])* $name:ident: $ty:ty = $field:ident) => {
+        $(#[$doc])*
+        pub enum $name {}
+
+        impl Get<$ty> for $name {
+            fn get() -> $ty {
+                ContractsConfig::current().$field
+            }
+        }
+    };
+}
+
+config_getter!(
+    /// Reads `ContractsConfig::max_code_len`.
+    MaxCodeLen: u32 = max_code_len
+);
+config_getter!(
+    /// Reads `ContractsConfig::max_storage_key_len`.
+    MaxStorageKeyLen: u32 = max_storage_key_len
+);
+config_getter!(
+    /// Reads `ContractsConfig::max_debug_buffer_len`.
+    MaxDebugBufferLen: u32 = max_debug_buffer_len
+);
+config_getter!(
+    /// Reads `ContractsConfig::deposit_per_byte`.
+    DepositPerByte: u128 = deposit_per_byte
+);
+config_getter!(
+    /// Reads `ContractsConfig::deposit_per_item`.
+    DepositPerItem: u128 = deposit_per_item
+);
+config_getter!(
+    /// Reads `ContractsConfig::default_deposit_limit`.
+    DefaultDepositLimit: u128 = default_deposit_limit
+);
+config_getter!(
+    /// Reads `ContractsConfig::code_hash_lockup_deposit_percent`.
+    CodeHashLockupDepositPercent: Perbill = code_hash_lockup_deposit_percent
+);
+config_getter!(
+    /// Reads `ContractsConfig::max_delegate_dependencies`.
+    MaxDelegateDependencies: u32 = max_delegate_dependencies
+);
+config_getter!(
+    /// Reads `ContractsConfig::unsafe_unstable_interface`.
+    UnsafeUnstableInterface: bool = unsafe_unstable_interface
+);
+
+/// The default schedule with the limits from `ContractsConfig::limits`.
+pub struct ConfiguredSchedule<R>(PhantomData<R>);
+
+impl<R: Config> Get<Schedule<R>> for ConfiguredSchedule<R> {
+    fn get() -> Schedule<R> {
+        Schedule {
+            limits: ContractsConfig::current().limits,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "session"))]
+mod tests {
+    use super::ContractsConfig;
+    use crate::{
+        minimal::{MinimalSandbox, MinimalSandboxRuntime},
+        pallet_contracts::Limits,
+        session::{bundle::tests::truthful, error::SessionError, Session, NO_ARGS},
+        Error,
+    };
+
+    const CONTRACT: &str = r#"
+(module
+    (import "seal0" "seal_return" (func $seal_return (param i32 i32 i32)))
+    (import "env" "memory" (memory 1 1))
+    (func (export "deploy"))
+    (func (export "call")
+        (call $seal_return (i32.const 0) (i32.const 0) (i32.const 0))
+    )
+)"#;
+
+    fn assert_invalid(config: ContractsConfig) {
+        assert!(matches!(
+            config.validate::<MinimalSandboxRuntime>(),
+            Err(Error::InvalidContractsConfig(_))
+        ));
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(ContractsConfig::default()
+            .validate::<MinimalSandboxRuntime>()
+            .is_ok());
+    }
+
+    #[test]
+    fn zero_limits_are_invalid() {
+        assert_invalid(ContractsConfig::default().with_max_code_len(0));
+        assert_invalid(ContractsConfig::default().with_max_storage_key_len(0));
+        assert_invalid(ContractsConfig::default().with_limits(Limits {
+            payload_len: 0,
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn inconsistent_limits_are_invalid() {
+        // Contracts of such size wouldn't fit in the runtime memory.
+        assert_invalid(ContractsConfig::default().with_max_code_len(1024 * 1024));
+        assert_invalid(ContractsConfig::default().with_limits(Limits {
+            runtime_memory: 1024 * 1024,
+            ..Default::default()
+        }));
+        assert_invalid(ContractsConfig::default().with_max_debug_buffer_len(256));
+    }
+
+    #[test]
+    fn invalid_config_is_not_applied() {
+        let mut session = Session::<MinimalSandbox>::default();
+
+        let result = session.set_contracts_config(ContractsConfig::default().with_max_code_len(0));
+
+        assert!(matches!(
+            result,
+            Err(SessionError::Drink(Error::InvalidContractsConfig(_)))
+        ));
+        assert_eq!(session.get_contracts_config(), &ContractsConfig::default());
+    }
+
+    #[test]
+    fn lowered_code_len_limit_makes_upload_fail() {
+        let code = wat::parse_str(CONTRACT).unwrap();
+        let mut session = Session::<MinimalSandbox>::default();
+
+        let old = session
+            .set_contracts_config(ContractsConfig::default().with_max_code_len(16))
+            .expect("Config should be valid");
+        assert_eq!(old, ContractsConfig::default());
+        assert!(matches!(
+            session.upload(code.clone()),
+            Err(SessionError::UploadFailed(_))
+        ));
+
+        session
+            .set_contracts_config(ContractsConfig::default())
+            .expect("Config should be valid");
+        assert!(session.upload(code).is_ok());
+    }
+
+    #[test]
+    fn code_len_limit_cannot_be_lowered_below_stored_code() {
+        let bundle = truthful();
+        let code_len = bundle.wasm.len() as u32;
+        let mut session = Session::<MinimalSandbox>::default();
+        let address = session
+            .deploy_bundle(bundle, "new", NO_ARGS, vec![], None)
+            .unwrap();
+
+        let result = session
+            .set_contracts_config(ContractsConfig::default().with_max_code_len(code_len - 1));
+        assert!(matches!(
+            result,
+            Err(SessionError::Drink(Error::InvalidContractsConfig(_)))
+        ));
+        assert_eq!(session.get_contracts_config(), &ContractsConfig::default());
+
+        session
+            .set_contracts_config(ContractsConfig::default().with_max_code_len(code_len))
+            .expect("Stored code should fit");
+        assert!(session
+            .call_with_address::<_, bool>(address, "get", NO_ARGS, None)
+            .is_ok_and(|result| result == Ok(true)));
+    }
+}
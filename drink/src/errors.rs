@@ -11,6 +11,12 @@ pub enum Error {
     /// Bundle loading and parsing has failed
     #[error("Loading the contract bundle has failed: {0}")]
     BundleLoadFailed(String),
+    /// Storage snapshot loading and parsing has failed
+    #[error("Loading the storage snapshot has failed: {0}")]
+    SnapshotLoadFailed(String),
+    /// The contracts pallet configuration is not valid
+    #[error("Invalid contracts pallet configuration: {0}")]
+    InvalidContractsConfig(String),
 }
 
 /// Every contract message wraps its return value in `Result<T, LangResult>`. This is the error
@@ -0,0 +1,55 @@
+//! Hex encoding of byte strings, as used by the textual formats of drink! (replay logs, storage
+//! dumps, RPC).
+
+use std::fmt::Write;
+
+/// Encodes `data` as a lowercase hex string with the `0x` prefix.
+pub fn encode(data: &[u8]) -> String {
+    data.iter().fold(String::from("0x"), |mut hex, byte| {
+        write!(hex, "{byte:02x}").expect("Writing to a `String` cannot fail");
+        hex
+    })
+}
+
+/// Decodes a hex string (the `0x` prefix is optional). Returns `None` if `hex` is not a valid hex
+/// string, e.g. if it has an odd number of digits or contains non-hex characters.
+pub fn decode(hex: &str) -> Option<Vec<u8>> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    // `from_str_radix` alone would also accept signs, like in `+f`.
+    if digits.len() % 2 != 0 || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_roundtrips() {
+        let data = vec![0x00, 0x0f, 0xab, 0xff];
+        assert_eq!(encode(&data), "0x000fabff");
+        assert_eq!(decode(&encode(&data)), Some(data));
+    }
+
+    #[test]
+    fn prefix_is_optional_and_case_is_ignored() {
+        assert_eq!(decode("0xAbCd"), Some(vec![0xab, 0xcd]));
+        assert_eq!(decode("abcd"), Some(vec![0xab, 0xcd]));
+        assert_eq!(decode("0x"), Some(vec![]));
+        assert_eq!(decode(""), Some(vec![]));
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        for hex in [
+            "0x€a", "€a", "0xabc", "0x0g", "+f", "0x-1", "0x 1", "0x0x00",
+        ] {
+            assert_eq!(decode(hex), None, "`{hex}` should be rejected");
+        }
+    }
+}
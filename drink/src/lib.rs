@@ -3,21 +3,39 @@
 
 #![warn(missing_docs)]
 
+pub mod chain_extension;
+pub mod contracts_config;
 pub mod errors;
+pub mod hex;
 pub mod pallet_contracts_debugging;
+pub mod runtime_call;
+mod sandbox;
 #[cfg(feature = "session")]
 pub mod session;
+pub mod snapshot;
 
 #[cfg(feature = "macros")]
 pub use drink_test_macro::{contract_bundle_provider, test};
 pub use errors::Error;
 pub use frame_support;
+#[doc(hidden)]
+pub use ink_sandbox;
+/// The original `create_sandbox!` macro of `ink_sandbox`. Unlike `drink::create_sandbox!`, the
+/// runtimes it creates don't support mocking runtime calls and instantiations, reject all runtime
+/// calls and ignore `ContractsConfig`.
+pub use ink_sandbox::create_sandbox as create_sandbox_upstream;
 pub use ink_sandbox::{
-    api as sandbox_api, create_sandbox, pallet_balances, pallet_contracts, pallet_timestamp,
-    sp_externalities, AccountId32, DispatchError, Sandbox, Ss58Codec, Weight,
+    api as sandbox_api, pallet_balances, pallet_contracts, pallet_timestamp, sp_externalities,
+    AccountId32, DispatchError, Sandbox, Ss58Codec, Weight,
 };
+#[doc(hidden)]
+pub use parity_scale_codec;
 #[cfg(feature = "session")]
-pub use session::mock::{mock_message, ContractMock, MessageMock, MockedCallResult, Selector};
+pub use session::mock::{
+    mock_message, mock_message_with_context, mock_message_with_outcome, CallContext, CatchAllMock,
+    ConstructorMock, ContractMock, Expectation, Fallback, MessageMock, MockOutcome, MockSpy,
+    MockedCall, MockedCallResult, MockingError, Selector, ValueMessageMock,
+};
 
 /// Main result type for the drink crate.
 pub type DrinkResult<T> = std::result::Result<T, Error>;
@@ -25,12 +43,12 @@ pub type DrinkResult<T> = std::result::Result<T, Error>;
 /// Minimal Sandbox runtime used for testing contracts with drink!.
 #[allow(missing_docs)]
 pub mod minimal {
-    use ink_sandbox::create_sandbox;
+    use crate::create_sandbox;
 
     // create_sandbox!(MinimalSandbox);
     create_sandbox!(
         MinimalSandbox,
-        (),
+        crate::chain_extension::DrinkChainExtension,
         crate::pallet_contracts_debugging::DrinkDebug
     );
 }
@@ -14,17 +14,38 @@
 //! So, in brief: pallet-contracts -> runtime interface -> runtime extension
 //!              |<-----------runtime side-------------->|<---user side--->|
 //!
+//! # Multiple extensions
+//!
+//! Externalities can hold at most one extension of a given type. To allow many tracers and
+//! interceptors to coexist, we register `TracerRegistry` and `InterceptorRegistry` as the
+//! extensions, and they dispatch callbacks further to all the registered parties (see their
+//! documentation for the ordering rules).
+//!
+//! # Contract instantiation
+//!
+//! The pallet doesn't reveal the code hash of a contract being instantiated through `Debug`.
+//! Therefore, runtimes should also use `DrinkAddressGenerator` as the `AddressGenerator`, which
+//! passes the instantiation details to the intercepting extension (and lets it override the new
+//! contract's address). Sandboxes created with `drink::create_sandbox!` do that by default.
+//!
 //! # Passing objects between runtime and runtime extension
 //!
 //! Unfortunately, runtime interface that lies between runtime, and the end-user accepts only
 //! simple argument types, and those that implement some specific traits. This means that usually,
 //! complex objects will be passed in their encoded form (`Vec<u8>` obtained with scale encoding).
 
+mod address;
 mod intercepting;
+mod registry;
 mod runtime;
 mod tracing;
 
-pub use runtime::{InterceptingExt, InterceptingExtT, NoopExt, TracingExt, TracingExtT};
+pub use address::DrinkAddressGenerator;
+pub use registry::{InterceptorRegistry, TracerRegistry};
+pub(crate) use runtime::contract_call_debugger;
+pub use runtime::{
+    InterceptingExt, InterceptingExtT, InterceptionContext, NoopExt, TracingExt, TracingExtT,
+};
 
 /// Main configuration parameter for the contracts pallet debugging. Provides all the necessary
 /// trait implementations.
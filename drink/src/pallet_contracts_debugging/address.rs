@@ -0,0 +1,42 @@
+use parity_scale_codec::{Decode, Encode};
+
+use crate::{
+    pallet_contracts::{AddressGenerator, Config, DefaultAddressGenerator},
+    pallet_contracts_debugging::runtime::contract_call_debugger,
+};
+
+/// Address generator that exposes contract instantiations to the intercepting extension.
+///
+/// Addresses are computed exactly as by `DefaultAddressGenerator`, unless an interceptor decides
+/// to override them. Since the generator is the only place where the pallet reveals the code hash
+/// of a contract being instantiated, it is also used to learn which code is behind the new
+/// address.
+pub enum DrinkAddressGenerator {}
+
+impl<R: Config> AddressGenerator<R> for DrinkAddressGenerator {
+    fn contract_address(
+        deploying_address: &R::AccountId,
+        code_hash: &<R as frame_system::Config>::Hash,
+        input_data: &[u8],
+        salt: &[u8],
+    ) -> R::AccountId {
+        let address = <DefaultAddressGenerator as AddressGenerator<R>>::contract_address(
+            deploying_address,
+            code_hash,
+            input_data,
+            salt,
+        );
+
+        contract_call_debugger::intercept_address(
+            deploying_address.encode(),
+            code_hash.encode(),
+            input_data.to_vec(),
+            salt.to_vec(),
+            address.encode(),
+        )
+        .and_then(|intercepting_result| {
+            Decode::decode(&mut intercepting_result.as_slice()).expect("Decoding should succeed")
+        })
+        .unwrap_or(address)
+    }
+}
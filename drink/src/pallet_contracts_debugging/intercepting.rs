@@ -1,12 +1,18 @@
+use frame_support::{
+    sp_runtime::traits::UniqueSaturatedInto,
+    traits::{fungible::Inspect, PalletInfo},
+};
 use ink_sandbox::AccountIdFor;
 use parity_scale_codec::{Decode, Encode};
 
 use crate::{
     pallet_contracts::{
         debug::{CallInterceptor, ExecResult, ExportedFunction},
-        Config,
+        Config, Pallet,
+    },
+    pallet_contracts_debugging::{
+        runtime::contract_call_debugger, DrinkDebug, InterceptionContext,
     },
-    pallet_contracts_debugging::{runtime::contract_call_debugger, DrinkDebug},
 };
 
 impl<R: Config> CallInterceptor<R> for DrinkDebug {
@@ -21,9 +27,54 @@ impl<R: Config> CallInterceptor<R> for DrinkDebug {
             contract_address.encode(),
             matches!(*entry_point, ExportedFunction::Call),
             input_data.to_vec(),
+            InterceptionContext {
+                block_number: frame_system::Pallet::<R>::block_number().unique_saturated_into(),
+                code_hash: Pallet::<R>::code_hash(contract_address).map(|hash| hash.encode()),
+                value: transferred_value::<R>(contract_address),
+            }
+            .encode(),
         )
         .and_then(|intercepting_result| {
             Decode::decode(&mut intercepting_result.as_slice()).expect("Decoding should succeed")
         })
     }
 }
+
+/// The variant index of `pallet_balances::Event::Transfer`.
+const BALANCES_TRANSFER_EVENT: u8 = 2;
+
+/// Returns the value transferred to `contract_address` with the call that is about to be executed.
+///
+/// The contracts pallet transfers the value right before the interception and doesn't expose it to
+/// the hooks. However, the transfer emits an event, which must be the last one emitted so far. If
+/// the last event is not a transfer to the contract (via the currency pallet), no value was
+/// transferred.
+fn transferred_value<R: Config>(contract_address: &AccountIdFor<R>) -> u128 {
+    let Some(currency_index) = <R as frame_system::Config>::PalletInfo::index::<R::Currency>()
+    else {
+        return 0;
+    };
+    let Some(record) = frame_system::Pallet::<R>::read_events_no_consensus().last() else {
+        return 0;
+    };
+
+    let event = record.event.encode();
+    let Some(([pallet, variant], mut fields)) = event.split_first_chunk::<2>() else {
+        return 0;
+    };
+    if *pallet as usize != currency_index || *variant != BALANCES_TRANSFER_EVENT {
+        return 0;
+    }
+
+    let (Ok(_from), Ok(to), Ok(amount)) = (
+        AccountIdFor::<R>::decode(&mut fields),
+        AccountIdFor::<R>::decode(&mut fields),
+        <R::Currency as Inspect<AccountIdFor<R>>>::Balance::decode(&mut fields),
+    ) else {
+        return 0;
+    };
+    match to == *contract_address && fields.is_empty() {
+        true => amount.unique_saturated_into(),
+        false => 0,
+    }
+}
@@ -0,0 +1,277 @@
+use std::sync::{Arc, Mutex};
+
+use parity_scale_codec::Encode;
+
+use crate::pallet_contracts_debugging::{InterceptingExtT, InterceptionContext, TracingExtT};
+
+/// An ordered collection of tracing extensions.
+///
+/// Since the externalities can hold only a single `TracingExt` instance, we register the registry
+/// itself as the extension and let it fan out every callback to all the tracers. Tracers are
+/// invoked in the order of their registration.
+///
+/// The registry is cheaply cloneable - all the clones share the same set of tracers, so that new
+/// tracers can be added after the registry has been moved into the sandbox.
+#[derive(Clone, Default)]
+pub struct TracerRegistry {
+    tracers: Arc<Mutex<Vec<Box<dyn TracingExtT + Send>>>>,
+}
+
+impl TracerRegistry {
+    /// Creates a new, empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `tracer` to the registry. It will be invoked after all the previously registered
+    /// tracers.
+    pub fn register(&self, tracer: Box<dyn TracingExtT + Send>) {
+        self.tracers
+            .lock()
+            .expect("Should be able to acquire tracer registry")
+            .push(tracer);
+    }
+
+    /// Returns the number of registered tracers.
+    pub fn len(&self) -> usize {
+        self.tracers
+            .lock()
+            .expect("Should be able to acquire tracer registry")
+            .len()
+    }
+
+    /// Returns `true` if there are no registered tracers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl TracingExtT for TracerRegistry {
+    fn before_call(&self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        for tracer in self
+            .tracers
+            .lock()
+            .expect("Should be able to acquire tracer registry")
+            .iter()
+        {
+            tracer.before_call(contract_address.clone(), is_call, input_data.clone());
+        }
+    }
+
+    fn after_call(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+        result: Vec<u8>,
+    ) {
+        for tracer in self
+            .tracers
+            .lock()
+            .expect("Should be able to acquire tracer registry")
+            .iter()
+        {
+            tracer.after_call(
+                contract_address.clone(),
+                is_call,
+                input_data.clone(),
+                result.clone(),
+            );
+        }
+    }
+
+    fn after_failed_call(&self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        for tracer in self
+            .tracers
+            .lock()
+            .expect("Should be able to acquire tracer registry")
+            .iter()
+        {
+            tracer.after_failed_call(contract_address.clone(), is_call, input_data.clone());
+        }
+    }
+
+    fn after_chain_extension_call(
+        &self,
+        contract_address: Vec<u8>,
+        ext_id: u16,
+        func_id: u16,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        ret_code: u32,
+    ) {
+        for tracer in self
+            .tracers
+            .lock()
+            .expect("Should be able to acquire tracer registry")
+            .iter()
+        {
+            tracer.after_chain_extension_call(
+                contract_address.clone(),
+                ext_id,
+                func_id,
+                input.clone(),
+                output.clone(),
+                ret_code,
+            );
+        }
+    }
+}
+
+/// An ordered chain of intercepting extensions.
+///
+/// Similarly to `TracerRegistry`, the chain is registered in the externalities as a single
+/// `InterceptingExt`. Interceptors are consulted in the order of their registration and the first
+/// one that decides to intercept the call short-circuits the chain: its result is used and the
+/// remaining interceptors are not invoked at all. If no interceptor claims the call, it is executed
+/// normally.
+#[derive(Clone, Default)]
+pub struct InterceptorRegistry {
+    interceptors: Arc<Mutex<Vec<Box<dyn InterceptingExtT + Send>>>>,
+}
+
+impl InterceptorRegistry {
+    /// Creates a new, empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `interceptor` to the chain. It will be consulted only if all the previously
+    /// registered interceptors let the call through.
+    pub fn register(&self, interceptor: Box<dyn InterceptingExtT + Send>) {
+        self.interceptors
+            .lock()
+            .expect("Should be able to acquire interceptor registry")
+            .push(interceptor);
+    }
+
+    /// Returns the number of registered interceptors.
+    pub fn len(&self) -> usize {
+        self.interceptors
+            .lock()
+            .expect("Should be able to acquire interceptor registry")
+            .len()
+    }
+
+    /// Returns `true` if there are no registered interceptors.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consults the interceptors in order and returns the first result that intercepts the call.
+    fn first_intercepting(&self, intercept: impl Fn(&dyn InterceptingExtT) -> Vec<u8>) -> Vec<u8> {
+        let pass = None::<()>.encode();
+
+        for interceptor in self
+            .interceptors
+            .lock()
+            .expect("Should be able to acquire interceptor registry")
+            .iter()
+        {
+            let result = intercept(interceptor.as_ref());
+            if result != pass {
+                return result;
+            }
+        }
+
+        pass
+    }
+}
+
+impl InterceptingExtT for InterceptorRegistry {
+    fn intercept_call(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+    ) -> Vec<u8> {
+        self.first_intercepting(|interceptor| {
+            interceptor.intercept_call(contract_address.clone(), is_call, input_data.clone())
+        })
+    }
+
+    fn intercept_call_with_context(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+        context: InterceptionContext,
+    ) -> Vec<u8> {
+        self.first_intercepting(|interceptor| {
+            interceptor.intercept_call_with_context(
+                contract_address.clone(),
+                is_call,
+                input_data.clone(),
+                context.clone(),
+            )
+        })
+    }
+
+    fn intercept_address(
+        &self,
+        deployer: Vec<u8>,
+        code_hash: Vec<u8>,
+        input_data: Vec<u8>,
+        salt: Vec<u8>,
+        address: Vec<u8>,
+    ) -> Vec<u8> {
+        self.first_intercepting(|interceptor| {
+            interceptor.intercept_address(
+                deployer.clone(),
+                code_hash.clone(),
+                input_data.clone(),
+                salt.clone(),
+                address.clone(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use parity_scale_codec::Encode;
+
+    use super::*;
+
+    struct Interceptor {
+        id: u8,
+        claims: bool,
+        log: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl InterceptingExtT for Interceptor {
+        fn intercept_call(&self, _: Vec<u8>, _: bool, _: Vec<u8>) -> Vec<u8> {
+            self.log.lock().unwrap().push(self.id);
+            match self.claims {
+                true => Some(self.id).encode(),
+                false => None::<()>.encode(),
+            }
+        }
+    }
+
+    #[test]
+    fn first_claiming_interceptor_short_circuits() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let registry = InterceptorRegistry::new();
+        for (id, claims) in [(1, false), (2, true), (3, true)] {
+            registry.register(Box::new(Interceptor {
+                id,
+                claims,
+                log: Arc::clone(&log),
+            }));
+        }
+
+        let result = registry.intercept_call(vec![], true, vec![]);
+
+        assert_eq!(result, Some(2u8).encode());
+        assert_eq!(*log.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn empty_chain_does_not_intercept() {
+        let result = InterceptorRegistry::new().intercept_call(vec![], true, vec![]);
+        assert_eq!(result, None::<()>.encode());
+    }
+}
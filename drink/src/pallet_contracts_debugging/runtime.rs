@@ -1,4 +1,4 @@
-use parity_scale_codec::Encode;
+use parity_scale_codec::{Decode, Encode};
 use sp_runtime_interface::runtime_interface;
 
 use crate::sp_externalities::{decl_extension, ExternalitiesExt};
@@ -11,6 +11,12 @@ use crate::sp_externalities::{decl_extension, ExternalitiesExt};
 /// traits. For simplicity, we just go with primitives and codec encoded data.
 #[runtime_interface]
 pub trait ContractCallDebugger {
+    fn before_call(&mut self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        if let Some(ext) = self.extension::<TracingExt>() {
+            ext.before_call(contract_address, is_call, input_data);
+        }
+    }
+
     fn after_call(
         &mut self,
         contract_address: Vec<u8>,
@@ -23,19 +29,65 @@ pub trait ContractCallDebugger {
         }
     }
 
+    fn after_failed_call(&mut self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        if let Some(ext) = self.extension::<TracingExt>() {
+            ext.after_failed_call(contract_address, is_call, input_data);
+        }
+    }
+
+    fn after_chain_extension_call(
+        &mut self,
+        contract_address: Vec<u8>,
+        ext_id: u16,
+        func_id: u16,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        ret_code: u32,
+    ) {
+        if let Some(ext) = self.extension::<TracingExt>() {
+            ext.after_chain_extension_call(
+                contract_address,
+                ext_id,
+                func_id,
+                input,
+                output,
+                ret_code,
+            );
+        }
+    }
+
     fn intercept_call(
         &mut self,
         contract_address: Vec<u8>,
         is_call: bool,
         input_data: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Option<Vec<u8>> {
+        let context = InterceptionContext::decode(&mut context.as_slice())
+            .expect("Interception context should be decodable");
+        self.extension::<InterceptingExt>().map(|ext| {
+            ext.intercept_call_with_context(contract_address, is_call, input_data, context)
+        })
+    }
+
+    fn intercept_address(
+        &mut self,
+        deployer: Vec<u8>,
+        code_hash: Vec<u8>,
+        input_data: Vec<u8>,
+        salt: Vec<u8>,
+        address: Vec<u8>,
     ) -> Option<Vec<u8>> {
         self.extension::<InterceptingExt>()
-            .map(|ext| ext.intercept_call(contract_address, is_call, input_data))
+            .map(|ext| ext.intercept_address(deployer, code_hash, input_data, salt, address))
     }
 }
 
 /// This trait describes a runtime extension that can be used to debug contract calls.
 pub trait TracingExtT {
+    /// Called just before a contract call is executed (after the value has been transferred).
+    fn before_call(&self, _contract_address: Vec<u8>, _is_call: bool, _input_data: Vec<u8>) {}
+
     /// Called after a contract call is made.
     fn after_call(
         &self,
@@ -45,6 +97,27 @@ pub trait TracingExtT {
         _result: Vec<u8>,
     ) {
     }
+
+    /// Called instead of `after_call` when the call has failed without producing any output (e.g.
+    /// the contract trapped or ran out of gas).
+    fn after_failed_call(&self, _contract_address: Vec<u8>, _is_call: bool, _input_data: Vec<u8>) {}
+
+    /// Called after the contract `contract_address` has successfully invoked function `func_id` of
+    /// the chain extension `ext_id`. `output` and `ret_code` are the data and the return code passed
+    /// back to the contract.
+    ///
+    /// Note: this callback is invoked only by chain extensions that report their calls (like
+    /// `DrinkChainExtension`).
+    fn after_chain_extension_call(
+        &self,
+        _contract_address: Vec<u8>,
+        _ext_id: u16,
+        _func_id: u16,
+        _input: Vec<u8>,
+        _output: Vec<u8>,
+        _ret_code: u32,
+    ) {
+    }
 }
 
 decl_extension! {
@@ -52,6 +125,59 @@ decl_extension! {
     pub struct TracingExt(Box<dyn TracingExtT + Send>);
 }
 
+impl TracingExtT for TracingExt {
+    fn before_call(&self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        self.0.before_call(contract_address, is_call, input_data)
+    }
+
+    fn after_call(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+        result: Vec<u8>,
+    ) {
+        self.0
+            .after_call(contract_address, is_call, input_data, result)
+    }
+
+    fn after_failed_call(&self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        self.0
+            .after_failed_call(contract_address, is_call, input_data)
+    }
+
+    fn after_chain_extension_call(
+        &self,
+        contract_address: Vec<u8>,
+        ext_id: u16,
+        func_id: u16,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        ret_code: u32,
+    ) {
+        self.0.after_chain_extension_call(
+            contract_address,
+            ext_id,
+            func_id,
+            input,
+            output,
+            ret_code,
+        )
+    }
+}
+
+/// Information about an intercepted call, gathered on the runtime side.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct InterceptionContext {
+    /// The number of the block in which the call is executed.
+    pub block_number: u64,
+    /// The encoded code hash of the called contract. `None` for constructor calls (the contract
+    /// doesn't exist yet).
+    pub code_hash: Option<Vec<u8>>,
+    /// The value transferred to the called contract with the call.
+    pub value: u128,
+}
+
 /// This trait describes a runtime extension that can be used to intercept contract calls.
 pub trait InterceptingExtT {
     /// Called when a contract call is made.
@@ -66,6 +192,38 @@ pub trait InterceptingExtT {
         // By default, do not intercept, continue with the standard procedure.
         None::<()>.encode()
     }
+
+    /// Same as `intercept_call`, but additionally receives the context of the call gathered on the
+    /// runtime side. This is the method that is actually invoked by the runtime - by default, it
+    /// forwards to `intercept_call`.
+    fn intercept_call_with_context(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+        _context: InterceptionContext,
+    ) -> Vec<u8> {
+        self.intercept_call(contract_address, is_call, input_data)
+    }
+
+    /// Called when the address of a new contract is being computed, i.e. just before the contract
+    /// is instantiated. `address` is the address computed by the default address generator.
+    ///
+    /// The returned value must be a valid codec encoding for `Option<AccountId>`. `Some` overrides
+    /// the address of the new contract.
+    ///
+    /// Note: this callback is invoked only if the runtime uses `DrinkAddressGenerator`.
+    fn intercept_address(
+        &self,
+        _deployer: Vec<u8>,
+        _code_hash: Vec<u8>,
+        _input_data: Vec<u8>,
+        _salt: Vec<u8>,
+        _address: Vec<u8>,
+    ) -> Vec<u8> {
+        // By default, keep the default address.
+        None::<()>.encode()
+    }
 }
 
 decl_extension! {
@@ -73,6 +231,40 @@ decl_extension! {
     pub struct InterceptingExt(Box<dyn InterceptingExtT + Send>);
 }
 
+impl InterceptingExtT for InterceptingExt {
+    fn intercept_call(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+    ) -> Vec<u8> {
+        self.0.intercept_call(contract_address, is_call, input_data)
+    }
+
+    fn intercept_call_with_context(
+        &self,
+        contract_address: Vec<u8>,
+        is_call: bool,
+        input_data: Vec<u8>,
+        context: InterceptionContext,
+    ) -> Vec<u8> {
+        self.0
+            .intercept_call_with_context(contract_address, is_call, input_data, context)
+    }
+
+    fn intercept_address(
+        &self,
+        deployer: Vec<u8>,
+        code_hash: Vec<u8>,
+        input_data: Vec<u8>,
+        salt: Vec<u8>,
+        address: Vec<u8>,
+    ) -> Vec<u8> {
+        self.0
+            .intercept_address(deployer, code_hash, input_data, salt, address)
+    }
+}
+
 /// The simplest extension - uses default implementation.
 pub struct NoopExt;
 impl TracingExtT for NoopExt {}
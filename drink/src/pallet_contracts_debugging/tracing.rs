@@ -1,11 +1,12 @@
 use ink_sandbox::AccountIdFor;
+use parity_scale_codec::Encode;
 
 use crate::{
     pallet_contracts::{
         debug::{CallSpan, ExportedFunction},
         Config, ExecReturnValue, Tracing,
     },
-    pallet_contracts_debugging::DrinkDebug,
+    pallet_contracts_debugging::{runtime::contract_call_debugger, DrinkDebug},
 };
 
 impl<R: Config> Tracing<R> for DrinkDebug {
@@ -16,10 +17,17 @@ impl<R: Config> Tracing<R> for DrinkDebug {
         entry_point: ExportedFunction,
         input_data: &[u8],
     ) -> Self::CallSpan {
+        contract_call_debugger::before_call(
+            contract_address.encode(),
+            matches!(entry_point, ExportedFunction::Call),
+            input_data.to_vec(),
+        );
+
         DrinkCallSpan {
             contract_address: contract_address.clone(),
             entry_point,
             input_data: input_data.to_vec(),
+            finished: false,
         }
     }
 }
@@ -27,19 +35,23 @@ impl<R: Config> Tracing<R> for DrinkDebug {
 /// A contract's call span.
 ///
 /// It is created just before the call is made and `Self::after_call` is called after the call is
-/// done.
-pub struct DrinkCallSpan<AccountId> {
+/// done. If the call fails, the pallet drops the span without calling `Self::after_call` - we
+/// detect this case in the `Drop` implementation.
+pub struct DrinkCallSpan<AccountId: Encode> {
     /// The address of the contract that has been called.
     pub contract_address: AccountId,
     /// The entry point that has been called (either constructor or call).
     pub entry_point: ExportedFunction,
     /// The input data of the call.
     pub input_data: Vec<u8>,
+    /// Whether `Self::after_call` has been called.
+    finished: bool,
 }
 
-impl<AccountId: parity_scale_codec::Encode> CallSpan for DrinkCallSpan<AccountId> {
-    fn after_call(self, output: &ExecReturnValue) {
-        crate::pallet_contracts_debugging::runtime::contract_call_debugger::after_call(
+impl<AccountId: Encode> CallSpan for DrinkCallSpan<AccountId> {
+    fn after_call(mut self, output: &ExecReturnValue) {
+        self.finished = true;
+        contract_call_debugger::after_call(
             self.contract_address.encode(),
             matches!(self.entry_point, ExportedFunction::Call),
             self.input_data.to_vec(),
@@ -47,3 +59,15 @@ impl<AccountId: parity_scale_codec::Encode> CallSpan for DrinkCallSpan<AccountId
         );
     }
 }
+
+impl<AccountId: Encode> Drop for DrinkCallSpan<AccountId> {
+    fn drop(&mut self) {
+        if !self.finished {
+            contract_call_debugger::after_failed_call(
+                self.contract_address.encode(),
+                matches!(self.entry_point, ExportedFunction::Call),
+                self.input_data.to_vec(),
+            );
+        }
+    }
+}
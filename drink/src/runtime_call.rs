@@ -0,0 +1,426 @@
+//! This module provides a call filter for the contracts pallet, which lets the tests decide (and
+//! observe) which runtime calls can be dispatched by contracts with `call_runtime`.
+//!
+//! The flow is analogous to the one of `chain_extension`:
+//! `DrinkCallFilter` (runtime side) -> runtime interface -> `RuntimeCallFilterExt` (user side).
+//! `Session` registers its own `RuntimeCallRegistry` as the extension, so the filter can be simply
+//! set with `session.runtime_calls().filter(|call: &RuntimeCall| ...)`.
+//!
+//! Additionally, the runtimes created with `drink::create_sandbox!` dispatch contract calls through
+//! `dispatch`, which asks the extension for a mocked result first - this way, a call can be mocked
+//! with `session.runtime_calls().mock(&call, Ok(()))` and it won't be dispatched at all.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime_interface::runtime_interface;
+
+use crate::{
+    frame_support::{
+        dispatch::{DispatchResult, DispatchResultWithPostInfo, PostDispatchInfo},
+        sp_runtime::traits::Dispatchable,
+        traits::Contains,
+    },
+    sp_externalities::{decl_extension, ExternalitiesExt},
+};
+
+/// Call filter that forwards the decision to the `RuntimeCallFilterExt` runtime extension.
+///
+/// If there is no such extension registered, all the calls are rejected (as with the `()` filter).
+pub enum DrinkCallFilter {}
+
+impl<Call: Encode> Contains<Call> for DrinkCallFilter {
+    fn contains(call: &Call) -> bool {
+        runtime_call_filter_forwarder::contains(call.encode())
+    }
+}
+
+/// Dispatches a runtime `call` made by a contract, unless the `RuntimeCallFilterExt` extension
+/// provides a mocked result for it. In that case, the mocked result is returned instead (and the
+/// call filter is not consulted).
+pub fn dispatch<Call: Dispatchable<PostInfo = PostDispatchInfo> + Encode>(
+    call: Call,
+    origin: Call::RuntimeOrigin,
+) -> DispatchResultWithPostInfo {
+    match runtime_call_filter_forwarder::mocked_result(call.encode()) {
+        Some(result) => DispatchResult::decode(&mut &*result)
+            .expect("Mocked dispatch result should be decodable")
+            .map(Into::into)
+            .map_err(Into::into),
+        None => call.dispatch(origin),
+    }
+}
+
+/// Runtime interface through which `DrinkCallFilter` reaches the `RuntimeCallFilterExt`.
+#[runtime_interface]
+pub trait RuntimeCallFilterForwarder {
+    /// Returns `true` if the (encoded) `call` is allowed by the session filter.
+    fn contains(&mut self, call: Vec<u8>) -> bool {
+        self.extension::<RuntimeCallFilterExt>()
+            .map(|ext| ext.contains(call))
+            .unwrap_or(false)
+    }
+
+    /// Returns the encoded result mocked for the (encoded) `call`, if any.
+    fn mocked_result(&mut self, call: Vec<u8>) -> Option<Vec<u8>> {
+        self.extension::<RuntimeCallFilterExt>()
+            .and_then(|ext| ext.mocked_result(call))
+    }
+}
+
+/// This trait describes a runtime extension that filters runtime calls dispatched by contracts.
+pub trait RuntimeCallFilterExtT {
+    /// Called when a contract tries to dispatch the (encoded) runtime `call`. Returns `true` if the
+    /// call is allowed.
+    fn contains(&self, call: Vec<u8>) -> bool;
+
+    /// Called before the (encoded) runtime `call` is dispatched. If it returns an (encoded)
+    /// `DispatchResult`, the call is not dispatched and the result is returned to the contract
+    /// instead. By default, no call is mocked.
+    fn mocked_result(&self, _call: Vec<u8>) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+decl_extension! {
+    /// A wrapper type for the `RuntimeCallFilterExtT` extension.
+    pub struct RuntimeCallFilterExt(Box<dyn RuntimeCallFilterExtT + Send>);
+}
+
+/// An untyped runtime call filter. Receives the encoded call and returns `true` if the call is
+/// allowed.
+pub type RuntimeCallFilter = Box<dyn FnMut(&[u8]) -> bool + Send>;
+
+/// A runtime call that a contract tried to dispatch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DispatchedRuntimeCall<Call> {
+    /// The call itself.
+    pub call: Call,
+    /// Whether the call passed the filter (mocked calls are always allowed).
+    pub allowed: bool,
+    /// The mocked result returned instead of dispatching the call, if the call was mocked.
+    pub mocked: Option<DispatchResult>,
+}
+
+impl DispatchedRuntimeCall<Vec<u8>> {
+    /// Decodes the call. Returns `None` if the call is not decodable as `Call`.
+    pub(crate) fn decode<Call: Decode>(self) -> Option<DispatchedRuntimeCall<Call>> {
+        Some(DispatchedRuntimeCall {
+            call: Call::decode(&mut &*self.call).ok()?,
+            allowed: self.allowed,
+            mocked: self.mocked,
+        })
+    }
+}
+
+struct RegistryState {
+    filter: RuntimeCallFilter,
+    /// Mocked results, by the encoded call.
+    mocks: BTreeMap<Vec<u8>, DispatchResult>,
+    /// Encoded calls that contracts tried to dispatch, in the order of execution.
+    calls: Vec<DispatchedRuntimeCall<Vec<u8>>>,
+}
+
+/// Holds the runtime call filter and mocks, and remembers all the calls that went through them.
+///
+/// By default, all the calls are rejected (dispatching runtime calls must be explicitly allowed with
+/// `allow_all` or `filter`) and none is mocked. The registry is cheaply cloneable -
+/// all the clones share the same state, so that the filter and mocks can be changed after the
+/// registry has been moved into the sandbox.
+///
+/// Note: the filter decides only whether the call is dispatched. A rejected call fails (the contract
+/// receives `CallRuntimeFailed`). To pretend that a call succeeded (or failed with a particular
+/// error) without dispatching it, mock it with `mock`.
+#[derive(Clone)]
+pub struct RuntimeCallRegistry {
+    state: Arc<Mutex<RegistryState>>,
+}
+
+impl Default for RuntimeCallRegistry {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RegistryState {
+                filter: Box::new(|_| false),
+                mocks: BTreeMap::new(),
+                calls: vec![],
+            })),
+        }
+    }
+}
+
+impl RuntimeCallRegistry {
+    /// Creates a new registry, rejecting all the calls.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a typed `filter`. Takes care of decoding the call - calls that cannot be decoded as
+    /// `Call` are rejected. Replaces the previous filter.
+    pub fn filter<Call: Decode, F: FnMut(&Call) -> bool + Send + 'static>(&self, mut filter: F) {
+        self.state().filter = Box::new(move |encoded| {
+            Call::decode(&mut &*encoded).map_or(false, |call| filter(&call))
+        });
+    }
+
+    /// Sets an untyped `filter`. Returns the previous filter.
+    pub fn set_filter(&self, filter: RuntimeCallFilter) -> RuntimeCallFilter {
+        std::mem::replace(&mut self.state().filter, filter)
+    }
+
+    /// Mocks `call`: whenever a contract dispatches it, `result` is returned to the contract
+    /// instead, without dispatching the call (so it has no effects, like events). Returns the
+    /// previous mocked result for this call, if any.
+    ///
+    /// Note: the message of `DispatchError::Other` is not preserved.
+    pub fn mock<Call: Encode>(
+        &self,
+        call: &Call,
+        result: DispatchResult,
+    ) -> Option<DispatchResult> {
+        self.state().mocks.insert(call.encode(), result)
+    }
+
+    /// Removes the mock for `call`. Returns the removed mocked result, if any.
+    pub fn remove_mock<Call: Encode>(&self, call: &Call) -> Option<DispatchResult> {
+        self.state().mocks.remove(&call.encode())
+    }
+
+    /// Allows all the calls.
+    pub fn allow_all(&self) {
+        self.state().filter = Box::new(|_| true);
+    }
+
+    /// Rejects all the calls.
+    pub fn deny_all(&self) {
+        self.state().filter = Box::new(|_| false);
+    }
+
+    /// Returns all the encoded calls seen so far and forgets them.
+    pub(crate) fn take_calls(&self) -> Vec<DispatchedRuntimeCall<Vec<u8>>> {
+        std::mem::take(&mut self.state().calls)
+    }
+
+    fn state(&self) -> MutexGuard<'_, RegistryState> {
+        self.state
+            .lock()
+            .expect("Should be able to acquire runtime call registry")
+    }
+}
+
+impl RuntimeCallFilterExtT for RuntimeCallRegistry {
+    fn contains(&self, call: Vec<u8>) -> bool {
+        let mut state = self.state();
+        let allowed = (state.filter)(&call);
+        state.calls.push(DispatchedRuntimeCall {
+            call,
+            allowed,
+            mocked: None,
+        });
+        allowed
+    }
+
+    fn mocked_result(&self, call: Vec<u8>) -> Option<Vec<u8>> {
+        let mut state = self.state();
+        let result = *state.mocks.get(&call)?;
+        state.calls.push(DispatchedRuntimeCall {
+            call,
+            allowed: true,
+            mocked: Some(result),
+        });
+        Some(result.encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use frame_support::sp_runtime::DispatchError;
+    use ink_sandbox::api::prelude::*;
+    use parity_scale_codec::Encode;
+
+    use super::{
+        DispatchedRuntimeCall, RuntimeCallFilterExt, RuntimeCallFilterExtT, RuntimeCallRegistry,
+    };
+    use crate::{
+        minimal::{MinimalSandbox, RuntimeCall, RuntimeEvent},
+        pallet_contracts::Determinism,
+        AccountId32, Sandbox,
+    };
+
+    /// Dispatches the (encoded) runtime call given as the input and returns the return code of
+    /// `call_runtime` (as `u32`).
+    const CALL_RUNTIME: &str = r#"
+(module
+    (import "seal0" "seal_input" (func $seal_input (param i32 i32)))
+    (import "seal0" "call_runtime" (func $call_runtime (param i32 i32) (result i32)))
+    (import "seal0" "seal_return" (func $seal_return (param i32 i32 i32)))
+    (import "env" "memory" (memory 1 1))
+
+    ;; [0, 4) input buffer size, [4, 8) return code, [8, 1032) input buffer
+    (data (i32.const 0) "\00\04")
+
+    (func (export "deploy"))
+
+    (func (export "call")
+        (call $seal_input (i32.const 8) (i32.const 0))
+        (i32.store
+            (i32.const 4)
+            (call $call_runtime (i32.const 8) (i32.load (i32.const 0)))
+        )
+        (call $seal_return (i32.const 0) (i32.const 4) (i32.const 4))
+    )
+)"#;
+
+    const SUCCESS: u32 = 0;
+    const CALL_RUNTIME_FAILED: u32 = 10;
+
+    fn remark() -> RuntimeCall {
+        RuntimeCall::System(frame_system::Call::remark_with_event {
+            remark: b"drink".to_vec(),
+        })
+    }
+
+    fn setup() -> (MinimalSandbox, RuntimeCallRegistry, AccountId32) {
+        let mut sandbox = MinimalSandbox::default();
+        let registry = RuntimeCallRegistry::new();
+        sandbox.register_extension(RuntimeCallFilterExt(Box::new(registry.clone())));
+
+        let address = sandbox
+            .deploy_contract(
+                wat::parse_str(CALL_RUNTIME).unwrap(),
+                0,
+                vec![],
+                vec![],
+                MinimalSandbox::default_actor(),
+                MinimalSandbox::default_gas_limit(),
+                None,
+            )
+            .result
+            .expect("Failed to deploy the contract")
+            .account_id;
+
+        (sandbox, registry, address)
+    }
+
+    /// Calls the contract with `call` and returns the return code of `call_runtime` and whether
+    /// the call has been actually dispatched (i.e. whether it emitted an event).
+    fn dispatch(
+        sandbox: &mut MinimalSandbox,
+        address: &AccountId32,
+        call: &RuntimeCall,
+    ) -> (u32, bool) {
+        let events_before = sandbox.events().len();
+        let result = sandbox
+            .call_contract(
+                address.clone(),
+                0,
+                call.encode(),
+                MinimalSandbox::default_actor(),
+                MinimalSandbox::default_gas_limit(),
+                None,
+                Determinism::Enforced,
+            )
+            .result
+            .expect("Failed to call the contract");
+        let code = u32::from_le_bytes(result.data.try_into().expect("Should return u32"));
+
+        let remarked = sandbox.events()[events_before..].iter().any(|record| {
+            matches!(
+                record.event,
+                RuntimeEvent::System(frame_system::Event::Remarked { .. })
+            )
+        });
+        (code, remarked)
+    }
+
+    #[test]
+    fn calls_are_rejected_by_default() {
+        let (mut sandbox, registry, address) = setup();
+
+        assert_eq!(
+            dispatch(&mut sandbox, &address, &remark()),
+            (CALL_RUNTIME_FAILED, false)
+        );
+        assert!(!registry.take_calls()[0].allowed);
+    }
+
+    #[test]
+    fn allowed_calls_are_dispatched_and_recorded() {
+        let (mut sandbox, registry, address) = setup();
+        registry.allow_all();
+
+        assert_eq!(dispatch(&mut sandbox, &address, &remark()), (SUCCESS, true));
+        assert_eq!(
+            registry.take_calls(),
+            vec![DispatchedRuntimeCall {
+                call: remark().encode(),
+                allowed: true,
+                mocked: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn filtered_calls_fail_and_are_recorded() {
+        let (mut sandbox, registry, address) = setup();
+        registry.filter(|call: &RuntimeCall| !matches!(call, RuntimeCall::System(_)));
+
+        assert_eq!(
+            dispatch(&mut sandbox, &address, &remark()),
+            (CALL_RUNTIME_FAILED, false)
+        );
+        assert_eq!(
+            registry.take_calls(),
+            vec![DispatchedRuntimeCall {
+                call: remark().encode(),
+                allowed: false,
+                mocked: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn mocked_calls_are_not_dispatched_and_are_recorded() {
+        let (mut sandbox, registry, address) = setup();
+        // The filter doesn't apply to mocked calls.
+        registry.deny_all();
+        registry.mock(&remark(), Ok(()));
+
+        assert_eq!(
+            dispatch(&mut sandbox, &address, &remark()),
+            (SUCCESS, false)
+        );
+
+        registry.mock(&remark(), Err(DispatchError::BadOrigin));
+        assert_eq!(
+            dispatch(&mut sandbox, &address, &remark()),
+            (CALL_RUNTIME_FAILED, false)
+        );
+
+        assert_eq!(
+            registry.take_calls(),
+            vec![
+                DispatchedRuntimeCall {
+                    call: remark().encode(),
+                    allowed: true,
+                    mocked: Some(Ok(())),
+                },
+                DispatchedRuntimeCall {
+                    call: remark().encode(),
+                    allowed: true,
+                    mocked: Some(Err(DispatchError::BadOrigin)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn undecodable_calls_are_rejected_by_typed_filter() {
+        let registry = RuntimeCallRegistry::new();
+        registry.filter(|_: &RuntimeCall| true);
+
+        assert!(!registry.contains(vec![0xff; 3]));
+    }
+}
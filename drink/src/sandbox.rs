@@ -0,0 +1,463 @@
+//! Sandbox runtimes tailored for drink!.
+//!
+//! The `create_sandbox!` macro mirrors the one from `ink_sandbox`, but the generated runtime is
+//! configured to cooperate with drink! features (like mocking contract instantiations) out of the
+//! box. The upstream macro hardcodes the whole configuration of the contracts pallet, so it cannot
+//! be wrapped - instead, the tests below check that both macros configure the runtimes the same
+//! way (apart from the drink! extension points).
+
+/// Creates a sandbox runtime (with the contracts pallet configured for drink!) and a struct
+/// implementing `Sandbox` for it.
+///
+/// Comparing to the `ink_sandbox::create_sandbox!` macro, the generated runtime uses
+/// `DrinkAddressGenerator` as the contract address generator and `DrinkCallFilter` as the call
+/// filter (so that contracts can use `call_runtime`, subject to the filter set in the session).
+/// Runtime calls made by contracts are dispatched with `drink::runtime_call::dispatch`, so they can
+/// be mocked as well.
+/// Limits and deposits of the contracts pallet are read from `ContractsConfig`, so they can be
+/// tuned at runtime.
+///
+/// # Variants
+///
+/// - `create_sandbox!(Name)` - no chain extension and no debugging.
+/// - `create_sandbox!(Name, ChainExtension, Debug)` - custom chain extension and `Debug`
+///   configuration of the contracts pallet (usually `DrinkDebug`).
+/// - `create_sandbox!(Name, ChainExtension, Debug, { PalletName: pallet_path, .. })` - additionally,
+///   includes custom pallets in the runtime (their `Config` must be implemented by the caller).
+///
+/// Each variant can be followed by `max_call_depth = N` (e.g. `create_sandbox!(Name,
+/// max_call_depth = 2)`), which sets the size of the contracts call stack, i.e. how many nested
+/// calls a contract can make. By default, it is 5 (as in `ink_sandbox::create_sandbox!`).
+///
+/// The runtime type is called `NameRuntime`. Restoring a snapshot of the sandbox keeps the
+/// registered extensions.
+#[macro_export]
+macro_rules! create_sandbox {
+    ($name:ident) => {
+        $crate::create_sandbox!($name, max_call_depth = 5);
+    };
+    ($name:ident, max_call_depth = $max_call_depth:expr) => {
+        $crate::ink_sandbox::paste::paste! {
+            $crate::create_sandbox!($name, [<$name Runtime>], (), (), {}, $max_call_depth);
+        }
+    };
+    ($name:ident, $chain_extension: ty, $debug: ty) => {
+        $crate::create_sandbox!($name, $chain_extension, $debug, max_call_depth = 5);
+    };
+    ($name:ident, $chain_extension: ty, $debug: ty, max_call_depth = $max_call_depth:expr) => {
+        $crate::ink_sandbox::paste::paste! {
+            $crate::create_sandbox!($name, [<$name Runtime>], $chain_extension, $debug, {}, $max_call_depth);
+        }
+    };
+    ($name:ident, $chain_extension: ty, $debug: ty, { $( $pallet_name:tt : $pallet:ident ),* $(,)? }) => {
+        $crate::create_sandbox!($name, $chain_extension, $debug, {
+            $(
+                $pallet_name : $pallet,
+            )*
+        }, max_call_depth = 5);
+    };
+    ($name:ident, $chain_extension: ty, $debug: ty, { $( $pallet_name:tt : $pallet:ident ),* $(,)? }, max_call_depth = $max_call_depth:expr) => {
+        $crate::ink_sandbox::paste::paste! {
+            $crate::create_sandbox!($name, [<$name Runtime>], $chain_extension, $debug, {
+                $(
+                    $pallet_name : $pallet,
+                )*
+            }, $max_call_depth);
+        }
+    };
+    ($sandbox:ident, $runtime:ident, $chain_extension: ty, $debug: ty, { $( $pallet_name:tt : $pallet:ident ),* $(,)? }, $max_call_depth:expr) => {
+        // Put all the boilerplate into an auxiliary module.
+        mod construct_runtime {
+            use $crate::{
+                frame_support::{
+                    construct_runtime, derive_impl,
+                    dispatch::{DispatchInfo, DispatchResultWithPostInfo, GetDispatchInfo},
+                    sp_runtime::{
+                        testing::H256,
+                        traits::{Convert, Dispatchable},
+                        AccountId32,
+                    },
+                    traits::{ConstU128, ConstU32, ConstU64, Currency, IsType, Randomness},
+                    weights::Weight,
+                },
+                ink_sandbox::frame_system::{self, EnsureSigned},
+            };
+
+            // Brings the types used by custom pallets into scope.
+            #[allow(unused_imports)]
+            use super::*;
+
+            construct_runtime!(
+                pub enum $runtime {
+                    System: $crate::ink_sandbox::frame_system,
+                    Balances: $crate::pallet_balances,
+                    Timestamp: $crate::pallet_timestamp,
+                    Contracts: $crate::pallet_contracts,
+                    $(
+                        $pallet_name: $pallet,
+                    )*
+                }
+            );
+
+            #[derive_impl(frame_system::config_preludes::SolochainDefaultConfig as frame_system::DefaultConfig)]
+            impl frame_system::Config for $runtime {
+                type Block = frame_system::mocking::MockBlockU32<$runtime>;
+                type Version = ();
+                type BlockHashCount = ConstU32<250>;
+                type AccountData = $crate::pallet_balances::AccountData<
+                    <$runtime as $crate::pallet_balances::Config>::Balance,
+                >;
+            }
+
+            impl $crate::pallet_balances::Config for $runtime {
+                type RuntimeEvent = RuntimeEvent;
+                type WeightInfo = ();
+                type Balance = u128;
+                type DustRemoval = ();
+                type ExistentialDeposit = ConstU128<1>;
+                type AccountStore = System;
+                type ReserveIdentifier = [u8; 8];
+                type FreezeIdentifier = ();
+                type MaxLocks = ();
+                type MaxReserves = ();
+                type MaxFreezes = ();
+                type RuntimeHoldReason = RuntimeHoldReason;
+                type RuntimeFreezeReason = RuntimeFreezeReason;
+            }
+
+            impl $crate::pallet_timestamp::Config for $runtime {
+                type Moment = u64;
+                type OnTimestampSet = ();
+                type MinimumPeriod = ConstU64<1>;
+                type WeightInfo = ();
+            }
+
+            pub enum SandboxRandomness {}
+            impl Randomness<H256, u32> for SandboxRandomness {
+                fn random(_subject: &[u8]) -> (H256, u32) {
+                    unreachable!("No randomness")
+                }
+            }
+
+            type BalanceOf = <Balances as Currency<AccountId32>>::Balance;
+            impl Convert<Weight, BalanceOf> for $runtime {
+                fn convert(w: Weight) -> BalanceOf {
+                    w.ref_time().into()
+                }
+            }
+
+            impl $crate::pallet_contracts::Config for $runtime {
+                type Time = Timestamp;
+                type Randomness = SandboxRandomness;
+                type Currency = Balances;
+                type RuntimeEvent = RuntimeEvent;
+                type RuntimeCall = DrinkRuntimeCall;
+                type CallFilter = $crate::runtime_call::DrinkCallFilter;
+                type WeightPrice = Self;
+                type WeightInfo = ();
+                type ChainExtension = $chain_extension;
+                type Schedule = $crate::contracts_config::ConfiguredSchedule<Self>;
+                type CallStack = [$crate::pallet_contracts::Frame<Self>; $max_call_depth];
+                type DepositPerByte = $crate::contracts_config::DepositPerByte;
+                type DepositPerItem = $crate::contracts_config::DepositPerItem;
+                type AddressGenerator = $crate::pallet_contracts_debugging::DrinkAddressGenerator;
+                type MaxCodeLen = $crate::contracts_config::MaxCodeLen;
+                type MaxStorageKeyLen = $crate::contracts_config::MaxStorageKeyLen;
+                type UnsafeUnstableInterface = $crate::contracts_config::UnsafeUnstableInterface;
+                type UploadOrigin = EnsureSigned<Self::AccountId>;
+                type InstantiateOrigin = EnsureSigned<Self::AccountId>;
+                type MaxDebugBufferLen = $crate::contracts_config::MaxDebugBufferLen;
+                type Migrations = ();
+                type DefaultDepositLimit = $crate::contracts_config::DefaultDepositLimit;
+                type Debug = $debug;
+                type Environment = ();
+                type ApiVersion = ();
+                type Xcm = ();
+                type CodeHashLockupDepositPercent = $crate::contracts_config::CodeHashLockupDepositPercent;
+                type MaxDelegateDependencies = $crate::contracts_config::MaxDelegateDependencies;
+                type RuntimeHoldReason = RuntimeHoldReason;
+            }
+
+            /// `RuntimeCall` as seen by the contracts pallet. Dispatching it goes through
+            /// `drink::runtime_call::dispatch`, so that calls made by contracts can be mocked.
+            #[repr(transparent)]
+            pub struct DrinkRuntimeCall(RuntimeCall);
+
+            impl Dispatchable for DrinkRuntimeCall {
+                type RuntimeOrigin = RuntimeOrigin;
+                type Config = <RuntimeCall as Dispatchable>::Config;
+                type Info = <RuntimeCall as Dispatchable>::Info;
+                type PostInfo = <RuntimeCall as Dispatchable>::PostInfo;
+
+                fn dispatch(
+                    self,
+                    origin: Self::RuntimeOrigin,
+                ) -> DispatchResultWithPostInfo {
+                    $crate::runtime_call::dispatch(self.0, origin)
+                }
+            }
+
+            impl GetDispatchInfo for DrinkRuntimeCall {
+                fn get_dispatch_info(&self) -> DispatchInfo {
+                    self.0.get_dispatch_info()
+                }
+            }
+
+            impl $crate::parity_scale_codec::Decode for DrinkRuntimeCall {
+                fn decode<I: $crate::parity_scale_codec::Input>(
+                    input: &mut I,
+                ) -> Result<Self, $crate::parity_scale_codec::Error> {
+                    <RuntimeCall as $crate::parity_scale_codec::Decode>::decode(input).map(Self)
+                }
+            }
+
+            impl From<RuntimeCall> for DrinkRuntimeCall {
+                fn from(call: RuntimeCall) -> Self {
+                    Self(call)
+                }
+            }
+
+            impl From<DrinkRuntimeCall> for RuntimeCall {
+                fn from(call: DrinkRuntimeCall) -> Self {
+                    call.0
+                }
+            }
+
+            impl IsType<RuntimeCall> for DrinkRuntimeCall {
+                fn from_ref(call: &RuntimeCall) -> &Self {
+                    // SAFETY: `DrinkRuntimeCall` is a `repr(transparent)` wrapper of `RuntimeCall`.
+                    unsafe { &*(call as *const RuntimeCall as *const Self) }
+                }
+
+                fn into_ref(&self) -> &RuntimeCall {
+                    &self.0
+                }
+
+                fn from_mut(call: &mut RuntimeCall) -> &mut Self {
+                    // SAFETY: `DrinkRuntimeCall` is a `repr(transparent)` wrapper of `RuntimeCall`.
+                    unsafe { &mut *(call as *mut RuntimeCall as *mut Self) }
+                }
+
+                fn into_mut(&mut self) -> &mut RuntimeCall {
+                    &mut self.0
+                }
+            }
+
+            /// Default initial balance for the default account.
+            pub const INITIAL_BALANCE: u128 = 1_000_000_000_000_000;
+            pub const DEFAULT_ACCOUNT: AccountId32 = AccountId32::new([1u8; 32]);
+
+            pub struct $sandbox {
+                ext: $crate::ink_sandbox::TestExternalities,
+            }
+
+            impl ::std::default::Default for $sandbox {
+                fn default() -> Self {
+                    let ext = $crate::ink_sandbox::macros::BlockBuilder::<$runtime>::new_ext(vec![(
+                        DEFAULT_ACCOUNT,
+                        INITIAL_BALANCE,
+                    )]);
+                    Self { ext }
+                }
+            }
+
+            impl $crate::Sandbox for $sandbox {
+                type Runtime = $runtime;
+
+                fn execute_with<T>(&mut self, execute: impl FnOnce() -> T) -> T {
+                    self.ext.execute_with(execute)
+                }
+
+                fn take_snapshot(&mut self) -> $crate::ink_sandbox::Snapshot {
+                    self.ext.commit_all().expect("Failed to commit changes");
+                    let mut backend = self.ext.as_backend();
+                    let storage_root = *backend.root();
+                    let storage = backend
+                        .backend_storage_mut()
+                        .drain()
+                        .into_iter()
+                        .filter(|(_, (_, ref_count))| *ref_count > 0)
+                        .collect();
+                    $crate::ink_sandbox::Snapshot {
+                        storage,
+                        storage_root,
+                    }
+                }
+
+                fn restore_snapshot(&mut self, snapshot: $crate::ink_sandbox::Snapshot) {
+                    // Flush the pending changes, so that they don't leak into the restored state.
+                    self.ext.commit_all().expect("Failed to commit changes");
+                    // Unlike `ink_sandbox`, replace only the backend, so that the registered
+                    // extensions are kept.
+                    self.ext.backend = $crate::ink_sandbox::TestExternalities::from_raw_snapshot(
+                        snapshot.storage,
+                        snapshot.storage_root,
+                        $crate::frame_support::sp_runtime::StateVersion::V1,
+                    )
+                    .backend;
+                }
+
+                fn dry_run<T>(&mut self, action: impl FnOnce(&mut Self) -> T) -> T {
+                    // Make a backup of the backend.
+                    let backend_backup = self.ext.as_backend();
+                    // Run the action, potentially modifying storage. Ensure, that there are no
+                    // pending changes that would affect the reverted backend.
+                    let result = action(self);
+                    self.ext
+                        .commit_all()
+                        .expect("Failed to commit changes");
+
+                    // Restore the backend.
+                    self.ext.backend = backend_backup;
+                    result
+                }
+
+                fn register_extension<E: ::core::any::Any + $crate::ink_sandbox::Extension>(
+                    &mut self,
+                    ext: E,
+                ) {
+                    self.ext.register_extension(ext);
+                }
+
+                fn initialize_block(
+                    height: frame_system::pallet_prelude::BlockNumberFor<Self::Runtime>,
+                    parent_hash: <Self::Runtime as frame_system::Config>::Hash,
+                ) {
+                    $crate::ink_sandbox::macros::BlockBuilder::<Self::Runtime>::initialize_block(
+                        height,
+                        parent_hash,
+                    )
+                }
+
+                fn finalize_block(
+                    height: frame_system::pallet_prelude::BlockNumberFor<Self::Runtime>,
+                ) -> <Self::Runtime as frame_system::Config>::Hash {
+                    $crate::ink_sandbox::macros::BlockBuilder::<Self::Runtime>::finalize_block(
+                        height,
+                    )
+                }
+
+                fn default_actor() -> $crate::ink_sandbox::AccountIdFor<Self::Runtime> {
+                    DEFAULT_ACCOUNT
+                }
+
+                fn get_metadata() -> $crate::ink_sandbox::RuntimeMetadataPrefixed {
+                    Self::Runtime::metadata()
+                }
+
+                fn convert_account_to_origin(
+                    account: $crate::ink_sandbox::AccountIdFor<Self::Runtime>,
+                ) -> <<Self::Runtime as frame_system::Config>::RuntimeCall as Dispatchable>::RuntimeOrigin {
+                    Some(account).into()
+                }
+            }
+        }
+
+        // Export runtime type itself, pallets and useful types from the auxiliary module.
+        pub use construct_runtime::{
+            $sandbox, $runtime, Balances, Contracts, PalletInfo, RuntimeCall, RuntimeEvent,
+            RuntimeHoldReason, RuntimeOrigin, System, Timestamp,
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::{align_of, size_of};
+
+    use frame_support::{sp_runtime::Perbill, traits::Get};
+    use ink_sandbox::api::prelude::*;
+    use parity_scale_codec::Encode;
+
+    use crate::{
+        contracts_config::ContractsConfig,
+        minimal::{MinimalSandbox, MinimalSandboxRuntime as Drink, RuntimeCall},
+        pallet_balances, pallet_contracts,
+        pallet_contracts::{Frame, Schedule},
+        pallet_timestamp, Sandbox,
+    };
+
+    mod upstream {
+        crate::create_sandbox_upstream!(UpstreamSandbox);
+    }
+    use upstream::{UpstreamSandbox, UpstreamSandboxRuntime as Upstream};
+
+    // Only the runtime is used.
+    #[allow(unused_imports)]
+    mod shallow {
+        crate::create_sandbox!(ShallowSandbox, max_call_depth = 2);
+    }
+
+    macro_rules! assert_same_config {
+        ($($config:path => $item:ident: $ty:ty),* $(,)?) => {
+            $(
+                assert_eq!(
+                    <<Drink as $config>::$item as Get<$ty>>::get(),
+                    <<Upstream as $config>::$item as Get<$ty>>::get(),
+                    "`{}` differs from `ink_sandbox::create_sandbox!`",
+                    stringify!($item),
+                );
+            )*
+        };
+    }
+
+    fn max_call_depth<R: pallet_contracts::Config>() -> usize {
+        size_of::<<R as pallet_contracts::Config>::CallStack>() / size_of::<Frame<R>>()
+    }
+
+    #[test]
+    fn runtime_configuration_matches_upstream() {
+        assert_same_config!(
+            frame_system::Config => BlockHashCount: u32,
+            pallet_balances::Config => ExistentialDeposit: u128,
+            pallet_timestamp::Config => MinimumPeriod: u64,
+            pallet_contracts::Config => DepositPerByte: u128,
+            pallet_contracts::Config => DepositPerItem: u128,
+            pallet_contracts::Config => DefaultDepositLimit: u128,
+            pallet_contracts::Config => MaxCodeLen: u32,
+            pallet_contracts::Config => MaxStorageKeyLen: u32,
+            pallet_contracts::Config => MaxDebugBufferLen: u32,
+            pallet_contracts::Config => UnsafeUnstableInterface: bool,
+            pallet_contracts::Config => CodeHashLockupDepositPercent: Perbill,
+            pallet_contracts::Config => MaxDelegateDependencies: u32,
+        );
+        assert_eq!(
+            <<Drink as pallet_contracts::Config>::Schedule as Get<Schedule<Drink>>>::get().encode(),
+            <<Upstream as pallet_contracts::Config>::Schedule as Get<Schedule<Upstream>>>::get()
+                .encode(),
+            "`Schedule` differs from `ink_sandbox::create_sandbox!`"
+        );
+        assert_eq!(max_call_depth::<Drink>(), max_call_depth::<Upstream>());
+    }
+
+    #[test]
+    fn runtime_call_wrapper_has_the_layout_of_runtime_call() {
+        // The `IsType` casts rely on it.
+        type DrinkRuntimeCall = <Drink as pallet_contracts::Config>::RuntimeCall;
+        assert_eq!(size_of::<DrinkRuntimeCall>(), size_of::<RuntimeCall>());
+        assert_eq!(align_of::<DrinkRuntimeCall>(), align_of::<RuntimeCall>());
+    }
+
+    #[test]
+    fn max_call_depth_can_be_configured() {
+        assert_eq!(max_call_depth::<shallow::ShallowSandboxRuntime>(), 2);
+        assert!(ContractsConfig::default()
+            .validate::<shallow::ShallowSandboxRuntime>()
+            .is_ok());
+    }
+
+    #[test]
+    fn genesis_matches_upstream() {
+        let mut drink = MinimalSandbox::default();
+        let mut upstream = UpstreamSandbox::default();
+
+        assert_eq!(
+            MinimalSandbox::default_actor(),
+            UpstreamSandbox::default_actor()
+        );
+        assert_eq!(
+            drink.free_balance(&MinimalSandbox::default_actor()),
+            upstream.free_balance(&UpstreamSandbox::default_actor())
+        );
+    }
+}
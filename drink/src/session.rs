@@ -3,41 +3,63 @@
 use std::{
     fmt::Debug,
     mem,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 pub use contract_transcode;
 use contract_transcode::ContractMessageTranscoder;
 use error::SessionError;
-use frame_support::{traits::fungible::Inspect, weights::Weight};
+use frame_support::{sp_runtime::SaturatedConversion, traits::fungible::Inspect, weights::Weight};
 use ink_sandbox::{
-    api::prelude::*, AccountIdFor, ContractExecResultFor, ContractInstantiateResultFor, Sandbox,
+    api::{contracts_api::decode_debug_buffer, prelude::*},
+    AccountIdFor, ContractExecResultFor, ContractInstantiateResultFor, Sandbox,
 };
-use parity_scale_codec::Decode;
+use parity_scale_codec::{Decode, Encode};
 pub use record::{EventBatch, Record};
+pub use trace::{CallTrace, ChainExtensionCall};
 
 use crate::{
+    chain_extension::{ChainExtensionExt, ChainExtensionRegistry},
+    contracts_config::ContractsConfig,
     minimal::MinimalSandboxRuntime,
+    pallet_balances,
     pallet_contracts::{Config, Determinism},
-    pallet_contracts_debugging::{InterceptingExt, TracingExt},
-    session::mock::MockRegistry,
+    pallet_contracts_debugging::{
+        InterceptingExt, InterceptingExtT, InterceptorRegistry, TracerRegistry, TracingExt,
+        TracingExtT,
+    },
+    pallet_timestamp,
+    runtime_call::{DispatchedRuntimeCall, RuntimeCallFilterExt, RuntimeCallRegistry},
+    session::{
+        mock::MockRegistry,
+        replay::{ReplayError, ReplayLog, ReplayStep, StepKind},
+        state::{SavedContract, SessionState},
+    },
+    snapshot::StorageDump,
 };
 
 pub mod mock;
-use mock::MockingExtension;
+use mock::{MockCallStackTracer, MockingExtension};
+pub mod builder;
 pub mod bundle;
 pub mod error;
 pub mod mocking_api;
 mod record;
+pub mod replay;
+pub mod scenario;
+mod state;
+mod trace;
 mod transcoding;
 
+pub use builder::{Genesis, GenesisFor, SessionBuilder};
 pub use bundle::ContractBundle;
 
 use self::mocking_api::MockingApi;
 use crate::{
     errors::MessageResult,
     // minimal::MinimalSandboxRuntime,
-    session::transcoding::TranscoderRegistry,
+    session::{trace::CallTraceCollector, transcoding::TranscoderRegistry},
 };
 
 type BalanceOf<R> = <<R as Config>::Currency as Inspect<AccountIdFor<R>>>::Balance;
@@ -50,6 +72,9 @@ type HashFor<R> = <R as frame_system::Config>::Hash;
 pub const NO_ARGS: &[String] = &[];
 /// Convenient value for an empty salt.
 pub const NO_SALT: Vec<u8> = vec![];
+/// Environment variable that, when set, makes every `Session` print the debug messages of
+/// contracts to stdout as soon as the corresponding contract interaction finishes.
+pub const DEBUG_PRINT_ENV_VAR: &str = "DRINK_PRINT_DEBUG";
 /// Convenient value for no endowment.
 ///
 /// Compatible with any runtime with `u128` as the balance type.
@@ -145,7 +170,20 @@ where
 
     transcoders: TranscoderRegistry<AccountIdFor<T::Runtime>>,
     record: Record<T::Runtime>,
+    genesis: GenesisFor<T::Runtime>,
     mocks: Arc<Mutex<MockRegistry<AccountIdFor<T::Runtime>>>>,
+
+    tracers: TracerRegistry,
+    interceptors: InterceptorRegistry,
+    chain_extension: ChainExtensionRegistry,
+    runtime_calls: RuntimeCallRegistry,
+    contracts_config: ContractsConfig,
+
+    call_tracing: bool,
+    call_traces: CallTraceCollector<AccountIdFor<T::Runtime>>,
+    debug_printing: bool,
+    replay_recording: bool,
+    replay_log: ReplayLog,
 }
 
 impl<T: Sandbox> Default for Session<T>
@@ -154,21 +192,166 @@ where
     T: Default,
 {
     fn default() -> Self {
-        let mocks = Arc::new(Mutex::new(MockRegistry::new()));
-        let mut sandbox = T::default();
-        sandbox.register_extension(InterceptingExt(Box::new(MockingExtension {
+        let mut mock_registry = MockRegistry::new();
+        mock_registry.set_origin(T::default_actor().encode());
+        mock_registry.set_gas_limit(T::default_gas_limit());
+        let mocks = Arc::new(Mutex::new(mock_registry));
+
+        let call_traces = CallTraceCollector::new();
+        let tracers = TracerRegistry::new();
+        tracers.register(Box::new(call_traces.clone()));
+        tracers.register(Box::new(MockCallStackTracer {
             mock_registry: Arc::clone(&mocks),
-        })));
+        }));
 
-        Self {
+        let interceptors = InterceptorRegistry::new();
+        // Mocks are always consulted first, so that user-defined interceptors cannot accidentally
+        // shadow them.
+        interceptors.register(Box::new(MockingExtension::<T::Runtime>::new(Arc::clone(
+            &mocks,
+        ))));
+
+        let mut sandbox = T::default();
+        let contracts_config = sandbox.execute_with(ContractsConfig::current);
+
+        let mut session = Self {
             sandbox,
             mocks,
+            tracers,
+            interceptors,
+            chain_extension: ChainExtensionRegistry::new(),
+            runtime_calls: RuntimeCallRegistry::new(),
+            contracts_config,
+            call_tracing: false,
+            call_traces,
+            debug_printing: std::env::var_os(DEBUG_PRINT_ENV_VAR).is_some(),
+            replay_recording: false,
+            replay_log: ReplayLog::default(),
             actor: T::default_actor(),
             gas_limit: T::default_gas_limit(),
             determinism: Determinism::Enforced,
             transcoders: TranscoderRegistry::new(),
             record: Default::default(),
+            genesis: Default::default(),
+        };
+        session.register_extensions();
+        session
+    }
+}
+
+impl<T: Sandbox + Default> Session<T>
+where
+    T::Runtime: Config + pallet_balances::Config + pallet_timestamp::Config,
+{
+    /// Returns a builder of a session with a predefined initial state (balances, codes, contracts,
+    /// block number and timestamp).
+    pub fn builder() -> SessionBuilder<T> {
+        SessionBuilder::new()
+    }
+}
+
+impl<T: Sandbox> Session<T>
+where
+    T::Runtime: Config,
+{
+    /// Saves the state of the session in the file under `path`: the full sandbox storage (including
+    /// contract storage), the known contracts, their transcoders and the paths of the bundles they
+    /// were deployed from.
+    ///
+    /// The rest of the session (like mocks, tracers or the record) is not saved.
+    pub fn save_state<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SessionError> {
+        let deployed = self.record.deploy_returns();
+        let registered = self
+            .transcoders
+            .contracts()
+            .filter(|address| !deployed.contains(address));
+
+        let contracts = deployed
+            .iter()
+            .map(|address| (address, true))
+            .chain(registered.map(|address| (address, false)))
+            .map(|(address, deployed)| {
+                // Absolute paths keep the state loadable from another working directory.
+                let source = self.transcoders.source(address).map(|path| {
+                    path.canonicalize()
+                        .unwrap_or_else(|_| path.to_path_buf())
+                        .to_string_lossy()
+                        .into_owned()
+                });
+                let metadata = self
+                    .transcoders
+                    .get(address)
+                    .map(|transcoder| serde_json::to_string(transcoder.metadata()))
+                    .transpose()
+                    .map_err(|err| {
+                        SessionError::StateFile(format!("Failed to serialize metadata: {err}"))
+                    })?;
+                Ok(SavedContract {
+                    address: address.encode(),
+                    deployed,
+                    source,
+                    metadata,
+                })
+            })
+            .collect::<Result<_, SessionError>>()?;
+
+        SessionState::new(self.sandbox.take_snapshot().into(), contracts).save(path.as_ref())
+    }
+
+    /// Loads the state saved with `save_state` from the file under `path`. The sandbox storage is
+    /// replaced, the saved transcoders are registered and the deployed contracts are appended to
+    /// the record (so that the last of them becomes the default target of `call`).
+    ///
+    /// If the file is invalid, the session is left intact.
+    pub fn load_state<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SessionError> {
+        let state = SessionState::load(path.as_ref())?;
+
+        let contracts = state
+            .contracts
+            .into_iter()
+            .map(|contract| {
+                let address = AccountIdFor::<T::Runtime>::decode(&mut contract.address.as_slice())
+                    .map_err(|err| {
+                        SessionError::StateFile(format!("Invalid contract address: {err}"))
+                    })?;
+                let transcoder = contract
+                    .metadata
+                    .map(|metadata| serde_json::from_str(&metadata))
+                    .transpose()
+                    .map_err(|err| SessionError::StateFile(format!("Invalid metadata: {err}")))?
+                    .map(|metadata| Arc::new(ContractMessageTranscoder::new(metadata)));
+                Ok((address, contract.deployed, contract.source, transcoder))
+            })
+            .collect::<Result<Vec<_>, SessionError>>()?;
+
+        for (address, deployed, source, transcoder) in contracts {
+            if let Some(transcoder) = transcoder {
+                self.transcoders.register(address.clone(), &transcoder);
+            }
+            if let Some(source) = source {
+                self.transcoders
+                    .register_source(address.clone(), PathBuf::from(source));
+            }
+            if deployed {
+                self.record.push_deploy_return(address);
+            }
         }
+
+        self.sandbox.restore_snapshot(state.snapshot.into());
+        // The sandbox may have replaced its externalities together with the extensions.
+        self.register_extensions();
+        self.refresh_contracts_config();
+        Ok(())
+    }
+
+    /// Registers the extensions through which the runtime reaches the session (tracers,
+    /// interceptors, chain extension and runtime call filter).
+    fn register_extensions(&mut self) {
+        let sandbox = &mut self.sandbox;
+        sandbox.register_extension(TracingExt(Box::new(self.tracers.clone())));
+        sandbox.register_extension(InterceptingExt(Box::new(self.interceptors.clone())));
+        sandbox.register_extension(ChainExtensionExt(Box::new(self.chain_extension.clone())));
+        sandbox.register_extension(RuntimeCallFilterExt(Box::new(self.runtime_calls.clone())));
     }
 }
 
@@ -177,8 +360,9 @@ where
     T::Runtime: Config,
 {
     /// Sets a new actor and returns updated `self`.
-    pub fn with_actor(self, actor: AccountIdFor<T::Runtime>) -> Self {
-        Self { actor, ..self }
+    pub fn with_actor(mut self, actor: AccountIdFor<T::Runtime>) -> Self {
+        self.set_actor(actor);
+        self
     }
 
     /// Returns currently set actor.
@@ -188,16 +372,25 @@ where
 
     /// Sets a new actor and returns the old one.
     pub fn set_actor(&mut self, actor: AccountIdFor<T::Runtime>) -> AccountIdFor<T::Runtime> {
+        self.mocks
+            .lock()
+            .expect("Should be able to acquire lock on registry")
+            .set_origin(actor.encode());
         mem::replace(&mut self.actor, actor)
     }
 
     /// Sets a new gas limit and returns updated `self`.
-    pub fn with_gas_limit(self, gas_limit: Weight) -> Self {
-        Self { gas_limit, ..self }
+    pub fn with_gas_limit(mut self, gas_limit: Weight) -> Self {
+        self.set_gas_limit(gas_limit);
+        self
     }
 
     /// Sets a new gas limit and returns the old one.
     pub fn set_gas_limit(&mut self, gas_limit: Weight) -> Weight {
+        self.mocks
+            .lock()
+            .expect("Should be able to acquire lock on registry")
+            .set_gas_limit(gas_limit);
         mem::replace(&mut self.gas_limit, gas_limit)
     }
 
@@ -206,6 +399,40 @@ where
         self.gas_limit
     }
 
+    /// Sets new parameters of the contracts pallet and returns updated `self`.
+    pub fn with_contracts_config(mut self, config: ContractsConfig) -> Result<Self, SessionError> {
+        self.set_contracts_config(config).map(|_| self)
+    }
+
+    /// Sets new parameters of the contracts pallet (like code size limit or deposits) and returns
+    /// the old ones. They take effect only if the sandbox runtime reads them from `ContractsConfig`
+    /// (which is the case for runtimes created with `drink::create_sandbox!`).
+    ///
+    /// Fails if the parameters are not valid for the runtime (see `ContractsConfig::validate`) or
+    /// if `max_code_len` is less than the length of an already stored code.
+    pub fn set_contracts_config(
+        &mut self,
+        config: ContractsConfig,
+    ) -> Result<ContractsConfig, SessionError> {
+        config.validate::<T::Runtime>()?;
+        config.apply(&mut self.sandbox)?;
+        Ok(mem::replace(&mut self.contracts_config, config))
+    }
+
+    /// Returns currently set parameters of the contracts pallet.
+    ///
+    /// Note: changes made directly to the sandbox storage (through `Session::sandbox`) are not
+    /// reflected here.
+    pub fn get_contracts_config(&self) -> &ContractsConfig {
+        &self.contracts_config
+    }
+
+    /// Reads the parameters of the contracts pallet from the sandbox storage again (after the
+    /// storage has been replaced).
+    fn refresh_contracts_config(&mut self) {
+        self.contracts_config = self.sandbox.execute_with(ContractsConfig::current);
+    }
+
     /// Sets a new determinism policy and returns updated `self`.
     pub fn with_determinism(self, determinism: Determinism) -> Self {
         Self {
@@ -219,6 +446,58 @@ where
         mem::replace(&mut self.determinism, determinism)
     }
 
+    /// Enables or disables call tracing and returns updated `self`.
+    ///
+    /// When call tracing is enabled, every contract interaction is recorded as a tree of
+    /// `CallTrace`s (available through `Record::call_traces`), with the calls decoded using
+    /// the transcoders registered for the respective contracts.
+    pub fn with_call_tracing(mut self, call_tracing: bool) -> Self {
+        self.set_call_tracing(call_tracing);
+        self
+    }
+
+    /// Enables or disables call tracing and returns the old setting.
+    pub fn set_call_tracing(&mut self, call_tracing: bool) -> bool {
+        self.call_traces.set_enabled(call_tracing);
+        mem::replace(&mut self.call_tracing, call_tracing)
+    }
+
+    /// Enables or disables printing contract debug messages to stdout and returns updated `self`.
+    ///
+    /// By default, printing is enabled only if the `DRINK_PRINT_DEBUG` environment variable is
+    /// set. Regardless of this setting, debug messages are always available in the `Record`.
+    pub fn with_debug_printing(self, debug_printing: bool) -> Self {
+        Self {
+            debug_printing,
+            ..self
+        }
+    }
+
+    /// Enables or disables printing contract debug messages to stdout and returns the old
+    /// setting.
+    pub fn set_debug_printing(&mut self, debug_printing: bool) -> bool {
+        mem::replace(&mut self.debug_printing, debug_printing)
+    }
+
+    /// Enables or disables replay recording and returns updated `self`.
+    pub fn with_replay_recording(self, replay_recording: bool) -> Self {
+        Self {
+            replay_recording,
+            ..self
+        }
+    }
+
+    /// Enables or disables replay recording and returns the previous setting. When enabled, all
+    /// contract deployments and calls are stored in the replay log (see `Session::replay_log`).
+    pub fn set_replay_recording(&mut self, replay_recording: bool) -> bool {
+        mem::replace(&mut self.replay_recording, replay_recording)
+    }
+
+    /// Returns the interactions recorded while replay recording was enabled.
+    pub fn replay_log(&self) -> &ReplayLog {
+        &self.replay_log
+    }
+
     /// Register a transcoder for a particular contract and returns updated `self`.
     pub fn with_transcoder(
         mut self,
@@ -243,6 +522,25 @@ where
         &mut self.sandbox
     }
 
+    /// Imports storage entries (e.g. of a real chain) from the file under `path` into the sandbox.
+    /// See `StorageDump` for the supported formats. The sandbox keeps its own runtime - only the
+    /// imported keys are overwritten.
+    ///
+    /// Contracts from the imported state are not known to the session, so they must be called with
+    /// an explicit address (e.g. `call_with_address`) after registering their transcoders with
+    /// `set_transcoder`.
+    pub fn import_storage<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SessionError> {
+        StorageDump::load(path)?.apply(&mut self.sandbox);
+        self.refresh_contracts_config();
+        Ok(())
+    }
+
+    /// Returns information about the initial state of the session (non-empty only for sessions
+    /// created with `Session::builder`).
+    pub fn genesis(&self) -> &GenesisFor<T::Runtime> {
+        &self.genesis
+    }
+
     /// Returns a reference to the record of the session.
     pub fn record(&self) -> &Record<T::Runtime> {
         &self.record
@@ -276,12 +574,66 @@ where
     }
     fn record_events<V>(&mut self, recording: impl FnOnce(&mut Self) -> V) -> V {
         let start = self.sandbox.events().len();
+        // Discard frames from any unrecorded interaction (like a dry run).
+        self.call_traces.take();
+        self.call_traces.take_chain_extension_calls();
+        self.runtime_calls.take_calls();
         let result = recording(self);
         let events = self.sandbox.events()[start..].to_vec();
         self.record.push_event_batches(events);
         result
     }
 
+    /// Saves debug messages of the last recorded interaction.
+    fn record_debug_messages(&mut self, debug_buffer: &[u8]) {
+        self.record
+            .push_debug_messages(decode_debug_buffer(debug_buffer));
+    }
+
+    /// Saves traces of the last recorded interaction, if call tracing is enabled, together with the
+    /// chain extension calls and runtime calls made during it (those are recorded always). Debug
+    /// messages are attached to the frame that printed them (when it can be determined) and
+    /// printed, if requested. Must be called after all the relevant transcoders have been
+    /// registered and after the debug messages have been recorded.
+    fn record_call_traces(&mut self) {
+        let mut traces = self.call_traces.take();
+        for trace in &mut traces {
+            trace.decode(&self.transcoders);
+        }
+
+        let messages = self.record.last_debug_messages();
+        // The collector yields the frames only when tracing is enabled, so the root frame is
+        // missing otherwise.
+        let frame = traces
+            .last_mut()
+            .and_then(|root| root.attribute_debug_messages(messages).then_some(root));
+        if self.debug_printing {
+            let frame = frame.and_then(|root| root.name());
+            for message in messages {
+                match &frame {
+                    Some(frame) => println!("[drink] {frame}: {message}"),
+                    None => println!("[drink] {message}"),
+                }
+            }
+        }
+
+        if self.call_tracing {
+            self.record.push_call_traces(traces);
+        }
+
+        let chain_extension_calls = self.call_traces.take_chain_extension_calls();
+        self.record
+            .push_chain_extension_calls(chain_extension_calls);
+
+        let runtime_calls = self
+            .runtime_calls
+            .take_calls()
+            .into_iter()
+            .filter_map(DispatchedRuntimeCall::decode)
+            .collect();
+        self.record.push_runtime_calls(runtime_calls);
+    }
+
     /// Deploys a contract with a given constructor, arguments, salt and endowment. In case of
     /// success, returns the address of the deployed contract.
     pub fn deploy<S: AsRef<str> + Debug>(
@@ -297,6 +649,7 @@ where
             .encode(constructor, args)
             .map_err(|err| SessionError::Encoding(err.to_string()))?;
 
+        let replay_salt = salt.clone();
         let result = self.record_events(|session| {
             session.sandbox.deploy_contract(
                 contract_bytes,
@@ -323,7 +676,19 @@ where
             Err(err) => Err(SessionError::DeploymentFailed(*err)),
         };
 
+        self.record_debug_messages(&result.debug_message);
         self.record.push_deploy_result(result);
+        self.record_call_traces();
+        self.record_replay_step(
+            StepKind::Deploy {
+                bundle: None,
+                constructor: constructor.to_string(),
+                salt: replay_salt,
+            },
+            args,
+            endowment,
+            ret.as_ref().map(Encode::encode),
+        );
         ret
     }
 
@@ -338,14 +703,25 @@ where
         salt: Vec<u8>,
         endowment: Option<BalanceOf<T::Runtime>>,
     ) -> Result<AccountIdFor<T::Runtime>, SessionError> {
-        self.deploy(
+        let path = contract_file.path().map(Path::to_path_buf);
+        let result = self.deploy(
             contract_file.wasm,
             constructor,
             args,
             salt,
             endowment,
             &contract_file.transcoder,
-        )
+        );
+        if let Some(path) = path {
+            if self.replay_recording {
+                self.replay_log
+                    .set_last_bundle(path.to_string_lossy().into_owned());
+            }
+            if let Ok(address) = &result {
+                self.transcoders.register_source(address.clone(), path);
+            }
+        }
+        result
     }
 
     /// Performs a dry run of the deployment of a contract.
@@ -545,6 +921,7 @@ where
             .encode(message, args)
             .map_err(|err| SessionError::Encoding(err.to_string()))?;
 
+        let replay_address = address.encode();
         let result = self.record_events(|session| {
             session.sandbox.call_contract(
                 address,
@@ -568,12 +945,197 @@ where
             Err(err) => Err(SessionError::CallFailed(*err)),
         };
 
+        self.record_debug_messages(&result.debug_message);
         self.record.push_call_result(result);
+        self.record_call_traces();
+        let outcome = match &ret {
+            Ok(_) => Ok(self.record.last_call_return().to_vec()),
+            Err(err) => Err(err),
+        };
+        self.record_replay_step(
+            StepKind::Call {
+                address: replay_address,
+                message: message.to_string(),
+            },
+            args,
+            endowment,
+            outcome,
+        );
         ret
     }
 
+    /// Stores the last recorded interaction in the replay log, if replay recording is enabled.
+    fn record_replay_step<S: AsRef<str>>(
+        &mut self,
+        kind: StepKind,
+        args: &[S],
+        endowment: Option<BalanceOf<T::Runtime>>,
+        outcome: Result<Vec<u8>, &SessionError>,
+    ) {
+        if !self.replay_recording {
+            return;
+        }
+        let step = ReplayStep {
+            kind,
+            actor: self.actor.encode(),
+            args: args.iter().map(|arg| arg.as_ref().to_string()).collect(),
+            value: endowment.encode(),
+            block: self.sandbox.block_number().saturated_into(),
+            outcome: outcome.map_err(ToString::to_string),
+            events: self.last_encoded_events(),
+        };
+        self.replay_log.push(step);
+    }
+
+    /// Returns the encoded events of the last recorded interaction.
+    fn last_encoded_events(&self) -> Vec<Vec<u8>> {
+        self.record
+            .last_event_batch()
+            .all_events()
+            .iter()
+            .map(Encode::encode)
+            .collect()
+    }
+
+    /// Replays `log` against this session: every step is performed by its recorded actor at (at
+    /// least) its recorded block, and its outcome and events are compared with the recorded ones.
+    /// Stops at the first mismatch. The session actor is restored afterwards.
+    pub fn replay(&mut self, log: &ReplayLog) -> Result<(), ReplayError> {
+        let actor = self.actor.clone();
+        let result = log
+            .steps()
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, step)| self.replay_step(index, step));
+        self.set_actor(actor);
+        result
+    }
+
+    fn replay_step(&mut self, index: usize, step: &ReplayStep) -> Result<(), ReplayError> {
+        let session_error = |error: SessionError| ReplayError::Session { step: index, error };
+        let format_error = |what: &str| {
+            ReplayError::Format(format!("Step {index}: invalid encoding of the {what}"))
+        };
+
+        let current: u64 = self.sandbox.block_number().saturated_into();
+        if step.block > current {
+            self.sandbox
+                .build_blocks((step.block - current).saturated_into());
+        }
+
+        let actor =
+            Decode::decode(&mut step.actor.as_slice()).map_err(|_| format_error("actor"))?;
+        self.set_actor(actor);
+        let endowment =
+            Decode::decode(&mut step.value.as_slice()).map_err(|_| format_error("value"))?;
+
+        let outcome = match &step.kind {
+            StepKind::Deploy {
+                bundle,
+                constructor,
+                salt,
+            } => {
+                let bundle = bundle
+                    .as_ref()
+                    .ok_or(ReplayError::UnknownBundle { step: index })?;
+                let bundle =
+                    ContractBundle::load(bundle).map_err(|err| session_error(err.into()))?;
+                self.deploy_bundle(bundle, constructor, &step.args, salt.clone(), endowment)
+                    .map(|address| address.encode())
+            }
+            StepKind::Call { address, message } => {
+                let address =
+                    Decode::decode(&mut address.as_slice()).map_err(|_| format_error("address"))?;
+                self.call_with_address::<_, ()>(address, message, &step.args, endowment)
+                    .map(|_| self.record.last_call_return().to_vec())
+            }
+        };
+
+        let outcome = match outcome {
+            // Errors raised before the interaction took place are not part of the replay.
+            Err(err @ (SessionError::Encoding(_) | SessionError::NoTranscoder)) => {
+                return Err(session_error(err))
+            }
+            outcome => outcome.map_err(|err| err.to_string()),
+        };
+
+        if outcome != step.outcome {
+            return Err(ReplayError::Mismatch {
+                step: index,
+                what: "outcome",
+                expected: format!("{:?}", step.outcome),
+                actual: format!("{outcome:?}"),
+            });
+        }
+
+        let events = self.last_encoded_events();
+        if events != step.events {
+            return Err(ReplayError::Mismatch {
+                step: index,
+                what: "events",
+                expected: format!("{:?}", step.events),
+                actual: format!("{events:?}"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds a tracer and returns updated `self`.
+    pub fn with_tracer(mut self, tracer: impl TracingExtT + Send + 'static) -> Self {
+        self.add_tracer(tracer);
+        self
+    }
+
+    /// Adds a tracer. It will be invoked for every contract call, after all the previously added
+    /// tracers.
+    pub fn add_tracer(&mut self, tracer: impl TracingExtT + Send + 'static) {
+        self.tracers.register(Box::new(tracer));
+    }
+
+    /// Adds an interceptor and returns updated `self`.
+    pub fn with_interceptor(mut self, interceptor: impl InterceptingExtT + Send + 'static) -> Self {
+        self.add_interceptor(interceptor);
+        self
+    }
+
+    /// Adds an interceptor. Interceptors are consulted in the order they were added (contract
+    /// mocks always go first) and the first one that intercepts a call decides its result.
+    pub fn add_interceptor(&mut self, interceptor: impl InterceptingExtT + Send + 'static) {
+        self.interceptors.register(Box::new(interceptor));
+    }
+
+    /// Returns the registry of chain extension function handlers.
+    ///
+    /// Handlers are used by `DrinkChainExtension`, i.e. only if the sandbox runtime is configured
+    /// with it (which is the case for `MinimalSandbox`). For example:
+    /// ```rust, ignore
+    /// session.chain_extension().on_typed(41, |(): ()| 100u32);
+    /// ```
+    pub fn chain_extension(&self) -> &ChainExtensionRegistry {
+        &self.chain_extension
+    }
+
+    /// Returns the registry holding the filter for runtime calls dispatched by contracts (with
+    /// `call_runtime`). By default, all the calls are rejected - allow them explicitly with
+    /// `RuntimeCallRegistry::allow_all` or `RuntimeCallRegistry::filter`.
+    ///
+    /// The filter is used by `DrinkCallFilter`, i.e. only if the sandbox runtime is configured with
+    /// it (which is the case for runtimes created with `drink::create_sandbox!`). Such runtimes also
+    /// support mocking the calls. All the calls that went through the filter or were mocked are
+    /// recorded (see `Record::runtime_calls`). For example:
+    /// ```rust, ignore
+    /// session.runtime_calls().filter(|call: &RuntimeCall| {
+    ///     matches!(call, RuntimeCall::Balances(_))
+    /// });
+    /// session.runtime_calls().mock(&call, Ok(()));
+    /// ```
+    pub fn runtime_calls(&self) -> &RuntimeCallRegistry {
+        &self.runtime_calls
+    }
+
     /// Set the tracing extension
+    #[deprecated(note = "Tracers are now composable, use `Session::add_tracer` instead")]
     pub fn set_tracing_extension(&mut self, d: TracingExt) {
-        self.sandbox.register_extension(d);
+        self.add_tracer(d);
     }
 }
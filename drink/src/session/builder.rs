@@ -0,0 +1,363 @@
+//! Declarative setup of a session's initial state.
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use frame_support::sp_runtime::{
+    traits::{BlakeTwo256, Hash, TrailingZeroInput},
+    SaturatedConversion,
+};
+use ink_sandbox::{api::prelude::*, AccountIdFor, Sandbox};
+use parity_scale_codec::Decode;
+
+use crate::{
+    pallet_balances,
+    pallet_contracts::Config,
+    pallet_timestamp,
+    session::{error::SessionError, BalanceOf, ContractBundle, HashFor, Session},
+    snapshot::StorageDump,
+};
+
+type GenesisBalanceOf<R> = <R as pallet_balances::Config>::Balance;
+/// An account funded at genesis, together with its balance.
+type GenesisAccountOf<R> = (AccountIdFor<R>, GenesisBalanceOf<R>);
+type MomentOf<R> = <R as pallet_timestamp::Config>::Moment;
+
+/// Returns the account derived from `name` (the same one that `SessionBuilder::with_named_account`
+/// funds).
+pub fn named_account<AccountId: Decode>(name: &str) -> AccountId {
+    let hash = BlakeTwo256::hash(name.as_bytes());
+    AccountId::decode(&mut TrailingZeroInput::new(hash.as_ref()))
+        .expect("Infinite input should be enough to decode an account")
+}
+
+/// A contract to be deployed when the session is built.
+struct GenesisContract<R: Config> {
+    name: String,
+    bundle: ContractBundle,
+    constructor: String,
+    args: Vec<String>,
+    salt: Vec<u8>,
+    endowment: Option<BalanceOf<R>>,
+}
+
+impl<R: Config> Clone for GenesisContract<R> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            bundle: self.bundle.clone(),
+            constructor: self.constructor.clone(),
+            args: self.args.clone(),
+            salt: self.salt.clone(),
+            endowment: self.endowment,
+        }
+    }
+}
+
+/// Builder of a `Session` with a predefined initial state.
+///
+/// The state is set up in the following order: imported storage, block number and timestamp,
+/// balances, code uploads, contract deployments. Contracts are deployed (and codes are uploaded) by
+/// the actor set with `SessionBuilder::with_actor`. As with imperative setup, the deployments are
+/// recorded in the session `Record`, so the last deployed contract is the default target of
+/// `Session::call`.
+///
+/// The builder is cheaply cloneable, so it can serve as a reusable fixture:
+/// ```rust, ignore
+/// fn fixture() -> SessionBuilder<MinimalSandbox> {
+///     Session::builder()
+///         .with_named_account("alice", 1_000_000)
+///         .with_contract("flipper", BundleProvider::local()?, "new", &["true"])
+/// }
+///
+/// let session = fixture().build()?;
+/// let flipper = session.genesis().contract("flipper");
+/// ```
+pub struct SessionBuilder<T: Sandbox>
+where
+    T::Runtime: Config + pallet_balances::Config + pallet_timestamp::Config,
+{
+    actor: Option<AccountIdFor<T::Runtime>>,
+    block_number: Option<u32>,
+    timestamp: Option<MomentOf<T::Runtime>>,
+    named_accounts: BTreeMap<String, AccountIdFor<T::Runtime>>,
+    balances: Vec<GenesisAccountOf<T::Runtime>>,
+    codes: Vec<Vec<u8>>,
+    contracts: Vec<GenesisContract<T::Runtime>>,
+    storage: Vec<StorageDump>,
+}
+
+impl<T: Sandbox> Clone for SessionBuilder<T>
+where
+    T::Runtime: Config + pallet_balances::Config + pallet_timestamp::Config,
+{
+    fn clone(&self) -> Self {
+        Self {
+            actor: self.actor.clone(),
+            block_number: self.block_number,
+            timestamp: self.timestamp,
+            named_accounts: self.named_accounts.clone(),
+            balances: self.balances.clone(),
+            codes: self.codes.clone(),
+            contracts: self.contracts.clone(),
+            storage: self.storage.clone(),
+        }
+    }
+}
+
+impl<T: Sandbox> Default for SessionBuilder<T>
+where
+    T::Runtime: Config + pallet_balances::Config + pallet_timestamp::Config,
+{
+    fn default() -> Self {
+        Self {
+            actor: None,
+            block_number: None,
+            timestamp: None,
+            named_accounts: BTreeMap::new(),
+            balances: vec![],
+            codes: vec![],
+            contracts: vec![],
+            storage: vec![],
+        }
+    }
+}
+
+impl<T: Sandbox + Default> SessionBuilder<T>
+where
+    T::Runtime: Config + pallet_balances::Config + pallet_timestamp::Config,
+{
+    /// Creates a new builder with an empty genesis (equivalent to `Session::default()`).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the actor of the session. It is also the account that uploads codes and deploys
+    /// contracts during the setup. By default, the sandbox default actor is used.
+    pub fn with_actor(mut self, actor: AccountIdFor<T::Runtime>) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Builds blocks until the block number is `block_number`.
+    pub fn with_block_number(mut self, block_number: u32) -> Self {
+        self.block_number = Some(block_number);
+        self
+    }
+
+    /// Sets the starting timestamp.
+    pub fn with_timestamp(mut self, timestamp: MomentOf<T::Runtime>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Mints `balance` to `account`.
+    pub fn with_balance(
+        mut self,
+        account: AccountIdFor<T::Runtime>,
+        balance: GenesisBalanceOf<T::Runtime>,
+    ) -> Self {
+        self.balances.push((account, balance));
+        self
+    }
+
+    /// Mints `balance` to the account derived from `name` (see `named_account`). The account can be
+    /// later obtained with `Genesis::account`.
+    pub fn with_named_account(self, name: &str, balance: GenesisBalanceOf<T::Runtime>) -> Self {
+        let account: AccountIdFor<T::Runtime> = named_account(name);
+        let mut builder = self.with_balance(account.clone(), balance);
+        builder.named_accounts.insert(name.to_string(), account);
+        builder
+    }
+
+    /// Imports storage entries (e.g. of a real chain). They are applied before anything else.
+    pub fn with_storage(mut self, storage: StorageDump) -> Self {
+        self.storage.push(storage);
+        self
+    }
+
+    /// Uploads the code of the contract from `bundle`.
+    pub fn with_code(mut self, bundle: ContractBundle) -> Self {
+        self.codes.push(bundle.wasm);
+        self
+    }
+
+    /// Deploys the contract from `bundle` with `constructor` and `args` (no salt, no endowment). The
+    /// address can be later obtained with `Genesis::contract`.
+    pub fn with_contract<S: AsRef<str>>(
+        self,
+        name: &str,
+        bundle: ContractBundle,
+        constructor: &str,
+        args: &[S],
+    ) -> Self {
+        self.with_contract_deployed(name, bundle, constructor, args, vec![], None)
+    }
+
+    /// Same as `with_contract`, but with an explicit salt and endowment.
+    pub fn with_contract_deployed<S: AsRef<str>>(
+        mut self,
+        name: &str,
+        bundle: ContractBundle,
+        constructor: &str,
+        args: &[S],
+        salt: Vec<u8>,
+        endowment: Option<BalanceOf<T::Runtime>>,
+    ) -> Self {
+        self.contracts.push(GenesisContract {
+            name: name.to_string(),
+            bundle,
+            constructor: constructor.to_string(),
+            args: args.iter().map(|arg| arg.as_ref().to_string()).collect(),
+            salt,
+            endowment,
+        });
+        self
+    }
+
+    /// Creates the session and sets up its initial state.
+    pub fn build(self) -> Result<Session<T>, SessionError> {
+        let mut session = Session::<T>::default();
+        if let Some(actor) = self.actor {
+            session.set_actor(actor);
+        }
+
+        for storage in &self.storage {
+            storage.apply(session.sandbox());
+        }
+        session.refresh_contracts_config();
+
+        if let Some(block_number) = self.block_number {
+            let current: u32 = session.sandbox().block_number().saturated_into();
+            if block_number > current {
+                session.sandbox().build_blocks(block_number - current);
+            }
+        }
+        if let Some(timestamp) = self.timestamp {
+            session.sandbox().set_timestamp(timestamp);
+        }
+
+        for (account, balance) in self.balances {
+            session
+                .sandbox()
+                .mint_into(&account, balance)
+                .map_err(SessionError::GenesisFailed)?;
+        }
+
+        let mut genesis = Genesis {
+            accounts: self.named_accounts,
+            ..Default::default()
+        };
+
+        for code in self.codes {
+            genesis.codes.push(session.upload(code)?);
+        }
+
+        for contract in self.contracts {
+            let address = session.deploy_bundle(
+                contract.bundle,
+                &contract.constructor,
+                contract.args.as_slice(),
+                contract.salt,
+                contract.endowment,
+            )?;
+            genesis.contracts.insert(contract.name, address);
+        }
+
+        session.genesis = genesis;
+        Ok(session)
+    }
+}
+
+/// Information about the initial state of a session built with `SessionBuilder`.
+#[derive(Clone, Debug)]
+pub struct Genesis<AccountId, CodeHash> {
+    accounts: BTreeMap<String, AccountId>,
+    contracts: BTreeMap<String, AccountId>,
+    codes: Vec<CodeHash>,
+}
+
+impl<AccountId, CodeHash> Default for Genesis<AccountId, CodeHash> {
+    fn default() -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            contracts: BTreeMap::new(),
+            codes: vec![],
+        }
+    }
+}
+
+impl<AccountId: Clone + Debug, CodeHash> Genesis<AccountId, CodeHash> {
+    /// Returns the account registered under `name`. Panics if there is no such account.
+    pub fn account(&self, name: &str) -> AccountId {
+        self.accounts
+            .get(name)
+            .unwrap_or_else(|| panic!("No genesis account named `{name}`"))
+            .clone()
+    }
+
+    /// Returns the address of the contract deployed under `name`. Panics if there is no such
+    /// contract.
+    pub fn contract(&self, name: &str) -> AccountId {
+        self.contracts
+            .get(name)
+            .unwrap_or_else(|| panic!("No genesis contract named `{name}`"))
+            .clone()
+    }
+
+    /// Returns the hashes of the uploaded codes, in the order of upload.
+    pub fn codes(&self) -> &[CodeHash] {
+        &self.codes
+    }
+}
+
+/// `Genesis` of a session running on the runtime `R`.
+pub type GenesisFor<R> = Genesis<AccountIdFor<R>, HashFor<R>>;
+
+#[cfg(test)]
+mod tests {
+    use ink_sandbox::AccountId32;
+
+    use super::*;
+    use crate::minimal::MinimalSandbox;
+
+    #[test]
+    fn named_accounts_are_deterministic() {
+        let alice: AccountId32 = named_account("alice");
+        assert_eq!(alice, named_account("alice"));
+        assert_ne!(alice, named_account::<AccountId32>("bob"));
+    }
+
+    #[test]
+    fn genesis_balances_are_minted() {
+        let account = AccountId32::new([7; 32]);
+        let mut session = SessionBuilder::<MinimalSandbox>::new()
+            .with_named_account("alice", 1_000_000)
+            .with_balance(account.clone(), 2_000_000)
+            .build()
+            .unwrap();
+
+        let alice = session.genesis().account("alice");
+        assert_eq!(alice, named_account("alice"));
+        assert_eq!(session.sandbox().free_balance(&alice), 1_000_000);
+        assert_eq!(session.sandbox().free_balance(&account), 2_000_000);
+    }
+
+    #[test]
+    fn defaults_match_default_session() {
+        let session = SessionBuilder::<MinimalSandbox>::new().build().unwrap();
+        assert_eq!(session.get_actor(), MinimalSandbox::default_actor());
+        assert_eq!(session.get_gas_limit(), MinimalSandbox::default_gas_limit());
+        assert!(session.genesis().codes().is_empty());
+
+        let actor = AccountId32::new([7; 32]);
+        let mut session = SessionBuilder::<MinimalSandbox>::new()
+            .with_actor(actor.clone())
+            .with_block_number(5)
+            .with_timestamp(42)
+            .build()
+            .unwrap();
+        assert_eq!(session.get_actor(), actor);
+        assert_eq!(session.sandbox().block_number(), 5);
+        assert_eq!(session.sandbox().get_timestamp(), 42);
+    }
+}
@@ -1,6 +1,9 @@
 //! This module provides simple utilities for loading and parsing `.contract` files in context of `drink` tests.
 
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use contract_metadata::ContractMetadata;
 use contract_transcode::ContractMessageTranscoder;
@@ -20,14 +23,30 @@ pub struct ContractBundle {
     pub wasm: Vec<u8>,
     /// Transcoder derived from the ABI/metadata
     pub transcoder: Arc<ContractMessageTranscoder>,
+    /// The path of the `.contract` file, if the bundle was loaded from disk.
+    path: Option<PathBuf>,
 }
 
 impl ContractBundle {
+    /// Creates a bundle from a WASM blob and a transcoder (not backed by any `.contract` file).
+    pub fn new(wasm: Vec<u8>, transcoder: Arc<ContractMessageTranscoder>) -> Self {
+        Self {
+            wasm,
+            transcoder,
+            path: None,
+        }
+    }
+
+    /// Returns the path of the `.contract` file, if the bundle was loaded from disk.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
     /// Load and parse the information in a `.contract` bundle under `path`, producing a
     /// `ContractBundle` struct.
     pub fn load<P>(path: P) -> DrinkResult<Self>
     where
-        P: AsRef<std::path::Path>,
+        P: AsRef<Path>,
     {
         let metadata: ContractMetadata = ContractMetadata::load(&path).map_err(|e| {
             Error::BundleLoadFailed(format!("Failed to load the contract file:\n{e:?}"))
@@ -50,7 +69,11 @@ impl ContractBundle {
             ))?
             .0;
 
-        Ok(Self { wasm, transcoder })
+        Ok(Self {
+            wasm,
+            transcoder,
+            path: Some(path.as_ref().to_path_buf()),
+        })
     }
 
     /// Load the `.contract` bundle (`contract_file_name`) located in the `project_dir`` working directory.
@@ -76,3 +99,101 @@ macro_rules! local_contract_file {
         )
     };
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{fs, process};
+
+    use ink_metadata::{
+        layout::{Layout, LayoutKey, LeafLayout},
+        ConstructorSpec, ContractSpec, EnvironmentSpec, EventSpec, InkProject, MessageSpec,
+        ReturnTypeSpec, TypeSpec,
+    };
+
+    use super::*;
+    use crate::hex;
+
+    /// A contract whose every message returns `Ok(true)`.
+    const TRUTHFUL: &str = r#"
+(module
+    (import "seal0" "seal_return" (func $seal_return (param i32 i32 i32)))
+    (import "env" "memory" (memory 1 1))
+    (data (i32.const 0) "\00\01")
+    (func (export "deploy"))
+    (func (export "call")
+        (call $seal_return (i32.const 0) (i32.const 0) (i32.const 2))
+    )
+)"#;
+
+    /// Metadata of `TRUTHFUL`: a `new` constructor and a `get` message.
+    fn truthful_metadata() -> InkProject {
+        let environment = EnvironmentSpec::new()
+            .account_id(TypeSpec::of_type::<[u8; 32]>())
+            .balance(TypeSpec::of_type::<u128>())
+            .hash(TypeSpec::of_type::<[u8; 32]>())
+            .timestamp(TypeSpec::of_type::<u64>())
+            .block_number(TypeSpec::of_type::<u32>())
+            .chain_extension(TypeSpec::of_type::<()>())
+            .max_event_topics(4)
+            .static_buffer_size(16384)
+            .done();
+        let spec = ContractSpec::new()
+            .constructors([ConstructorSpec::from_label("new")
+                .selector([0; 4])
+                .payable(false)
+                .returns(ReturnTypeSpec::new(TypeSpec::of_type::<()>()))
+                .done()])
+            .messages([MessageSpec::from_label("get")
+                .selector([1; 4])
+                .mutates(false)
+                .payable(false)
+                .returns(ReturnTypeSpec::new(TypeSpec::of_type::<Result<bool, ()>>()))
+                .done()])
+            .events(Vec::<EventSpec>::new())
+            .environment(environment)
+            .done();
+        let layout = Layout::Leaf(LeafLayout::from_key::<bool>(LayoutKey::new(0u32)));
+
+        InkProject::new(layout, spec)
+    }
+
+    /// The bundle of a contract whose every message returns `Ok(true)`, with a `new` constructor
+    /// and a `get` message.
+    pub(crate) fn truthful() -> ContractBundle {
+        ContractBundle::new(
+            wat::parse_str(TRUTHFUL).unwrap(),
+            Arc::new(ContractMessageTranscoder::new(truthful_metadata())),
+        )
+    }
+
+    /// Writes the `truthful` bundle as `truthful.contract` into a fresh directory named after
+    /// `test`, and returns the directory.
+    pub(crate) fn save_truthful(test: &str) -> PathBuf {
+        let mut bundle = serde_json::to_value(truthful_metadata()).unwrap();
+        bundle["source"] = serde_json::json!({
+            "hash": hex::encode(&[0; 32]),
+            "language": "ink! 5.0.0",
+            "compiler": "rustc 1.81.0",
+            "wasm": hex::encode(&truthful().wasm),
+        });
+        bundle["contract"] = serde_json::json!({
+            "name": "truthful",
+            "version": "0.1.0",
+            "authors": [],
+        });
+
+        let dir = std::env::temp_dir().join(format!("drink-{}-{test}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("truthful.contract"), bundle.to_string()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn saved_bundle_is_loaded() {
+        let dir = save_truthful("bundle-load");
+        let bundle = ContractBundle::load(dir.join("truthful.contract")).unwrap();
+        assert_eq!(bundle.wasm, truthful().wasm);
+        assert_eq!(bundle.path(), Some(dir.join("truthful.contract").as_path()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
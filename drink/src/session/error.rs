@@ -33,6 +33,12 @@ pub enum SessionError {
     /// Contract call failed (aborted by the pallet).
     #[error("Contract call failed before execution: {0:?}")]
     CallFailed(DispatchError),
+    /// Setting up the initial state of the session failed.
+    #[error("Genesis setup failed: {0:?}")]
+    GenesisFailed(DispatchError),
+    /// Saving or loading the session state failed.
+    #[error("Session state file error: {0}")]
+    StateFile(String),
     /// There is no deployed contract to call.
     #[error("No deployed contract")]
     NoContract,
@@ -1,21 +1,50 @@
 //! Mocking utilities for contract calls.
 
+mod constructor;
+mod context;
 mod contract;
 mod error;
 mod extension;
+mod outcome;
+mod spy;
 use std::collections::BTreeMap;
 
-pub use contract::{mock_message, ContractMock, MessageMock, Selector};
-use error::MockingError;
-pub(crate) use extension::MockingExtension;
+pub use constructor::ConstructorMock;
+pub use context::CallContext;
+pub use contract::{
+    mock_message, mock_message_with_context, mock_message_with_outcome, CatchAllMock, ContractMock,
+    Fallback, MessageMock, Selector, ValueMessageMock,
+};
+pub use error::MockingError;
+pub(crate) use extension::{MockCallStackTracer, MockingExtension};
+pub use outcome::MockOutcome;
+pub use spy::{Expectation, MockSpy, MockedCall};
+
+use crate::Weight;
 
 /// Untyped result of a mocked call.
-pub type MockedCallResult = Result<Vec<u8>, MockingError>;
+pub type MockedCallResult = Result<MockOutcome, MockingError>;
 
 /// A registry of mocked contracts.
 pub(crate) struct MockRegistry<AccountId: Ord> {
     mocked_contracts: BTreeMap<AccountId, ContractMock>,
+    /// Mocks of all the contract instances with a given code, keyed by the encoded code hash.
+    mocked_code: BTreeMap<Vec<u8>, ContractMock>,
+    /// Constructor mocks, keyed by the encoded code hash.
+    mocked_constructors: BTreeMap<Vec<u8>, ConstructorMock>,
+    /// Encoded code hashes of the mocked contracts that are being instantiated, keyed by the
+    /// encoded addresses of the new contracts.
+    pending_instantiations: BTreeMap<Vec<u8>, Vec<u8>>,
     nonce: u8,
+    /// Encoded addresses of the contracts that are currently being executed. The last one is the
+    /// innermost frame.
+    call_stack: Vec<Vec<u8>>,
+    /// Encoded address of the account that originates contract interactions (the session actor).
+    origin: Vec<u8>,
+    /// Gas limit of contract interactions (the session gas limit).
+    gas_limit: Weight,
+    /// Errors raised by the mocks during contract execution (which trapped the mocked calls).
+    errors: Vec<MockingError>,
 }
 
 impl<AccountId: Ord> MockRegistry<AccountId> {
@@ -23,7 +52,14 @@ impl<AccountId: Ord> MockRegistry<AccountId> {
     pub fn new() -> Self {
         Self {
             mocked_contracts: BTreeMap::new(),
+            mocked_code: BTreeMap::new(),
+            mocked_constructors: BTreeMap::new(),
+            pending_instantiations: BTreeMap::new(),
             nonce: 0u8,
+            call_stack: vec![],
+            origin: vec![],
+            gas_limit: Weight::zero(),
+            errors: vec![],
         }
     }
 
@@ -38,8 +74,161 @@ impl<AccountId: Ord> MockRegistry<AccountId> {
         self.mocked_contracts.insert(address, mock)
     }
 
-    /// Returns the mock for `address`, if any.
-    pub fn get(&self, address: &AccountId) -> Option<&ContractMock> {
-        self.mocked_contracts.get(address)
+    /// Registers `mock` for all the contracts with the (encoded) `code_hash`. Returns the previous
+    /// mock, if any.
+    pub fn register_code(
+        &mut self,
+        code_hash: Vec<u8>,
+        mock: ContractMock,
+    ) -> Option<ContractMock> {
+        self.mocked_code.insert(code_hash, mock)
+    }
+
+    /// Returns the mock for the contract at `address` with the (encoded) `code_hash`, if any. Mocks
+    /// registered for the address take precedence over the ones registered for the code hash.
+    pub fn find_mut(
+        &mut self,
+        address: &AccountId,
+        code_hash: Option<&[u8]>,
+    ) -> Option<&mut ContractMock> {
+        match self.mocked_contracts.get_mut(address) {
+            Some(mock) => Some(mock),
+            None => self.mocked_code.get_mut(code_hash?),
+        }
+    }
+
+    /// Registers constructor `mock` for the (encoded) `code_hash`. Returns the previous mock, if
+    /// any.
+    pub fn register_constructor(
+        &mut self,
+        code_hash: Vec<u8>,
+        mock: ConstructorMock,
+    ) -> Option<ConstructorMock> {
+        self.mocked_constructors.insert(code_hash, mock)
+    }
+
+    /// Notes that a contract with `code_hash` is about to be instantiated at (the default)
+    /// `address`. If the code is mocked, returns the address overridden by the mock, if any.
+    pub fn note_instantiation(&mut self, code_hash: Vec<u8>, address: Vec<u8>) -> Option<Vec<u8>> {
+        let mock = self.mocked_constructors.get_mut(&code_hash)?;
+        let overridden = mock.next_address();
+        self.pending_instantiations
+            .insert(overridden.clone().unwrap_or(address), code_hash);
+        overridden
+    }
+
+    /// Returns the constructor mock for the contract being instantiated at `address`, if any.
+    pub fn take_instantiation(&mut self, address: &[u8]) -> Option<&mut ConstructorMock> {
+        let code_hash = self.pending_instantiations.remove(address)?;
+        self.mocked_constructors.get_mut(&code_hash)
+    }
+
+    /// Notes that a frame of the contract at (encoded) `address` has started.
+    ///
+    /// When a new contract interaction starts, instantiations noted before are stale (they failed
+    /// before reaching the constructor), except for the instantiation of the top-level frame
+    /// itself. They are forgotten, so that they don't affect later deployments.
+    pub fn enter(&mut self, address: Vec<u8>, is_call: bool) {
+        if self.call_stack.is_empty() {
+            self.pending_instantiations
+                .retain(|pending, _| !is_call && *pending == address);
+        }
+        self.call_stack.push(address);
+    }
+
+    /// Notes that the innermost frame has finished. When the whole contract interaction is over,
+    /// the instantiations that didn't reach the constructor are forgotten.
+    pub fn exit(&mut self) {
+        self.call_stack.pop();
+        if self.call_stack.is_empty() {
+            self.pending_instantiations.clear();
+        }
+    }
+
+    /// Records an error raised by a mock during contract execution.
+    pub fn record_error(&mut self, error: MockingError) {
+        self.errors.push(error);
+    }
+
+    /// Returns the errors raised by the mocks during contract execution so far.
+    pub fn errors(&self) -> &[MockingError] {
+        &self.errors
+    }
+
+    /// Returns all the registered mocks.
+    pub fn mocks(&self) -> impl Iterator<Item = &ContractMock> {
+        self.mocked_contracts
+            .values()
+            .chain(self.mocked_code.values())
+    }
+
+    /// Sets the (encoded) account that originates contract interactions.
+    pub fn set_origin(&mut self, origin: Vec<u8>) {
+        self.origin = origin;
+    }
+
+    /// Sets the gas limit of contract interactions.
+    pub fn set_gas_limit(&mut self, gas_limit: Weight) {
+        self.gas_limit = gas_limit;
+    }
+
+    /// Returns the gas limit of contract interactions.
+    pub fn gas_limit(&self) -> Weight {
+        self.gas_limit
+    }
+
+    /// Returns the encoded address of the caller of the currently executed contract.
+    pub fn caller(&self) -> Vec<u8> {
+        match self.call_stack.len() {
+            0 | 1 => self.origin.clone(),
+            depth => self.call_stack[depth - 2].clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE_HASH: [u8; 1] = [7];
+    const ADDRESS: [u8; 1] = [1];
+
+    fn registry() -> MockRegistry<u32> {
+        let mut registry = MockRegistry::new();
+        registry.register_constructor(CODE_HASH.to_vec(), ConstructorMock::new());
+        registry
+    }
+
+    #[test]
+    fn noted_instantiation_reaches_constructor() {
+        let mut registry = registry();
+
+        registry.note_instantiation(CODE_HASH.to_vec(), ADDRESS.to_vec());
+        registry.enter(ADDRESS.to_vec(), false);
+
+        assert!(registry.take_instantiation(&ADDRESS).is_some());
+    }
+
+    #[test]
+    fn failed_top_level_instantiation_is_forgotten() {
+        let mut registry = registry();
+
+        // The instantiation fails before its frame is entered...
+        registry.note_instantiation(CODE_HASH.to_vec(), ADDRESS.to_vec());
+        // ...and the next interaction is a call.
+        registry.enter(vec![2], true);
+
+        assert!(registry.take_instantiation(&ADDRESS).is_none());
+    }
+
+    #[test]
+    fn failed_nested_instantiation_is_forgotten() {
+        let mut registry = registry();
+
+        registry.enter(vec![2], true);
+        registry.note_instantiation(CODE_HASH.to_vec(), ADDRESS.to_vec());
+        registry.exit();
+
+        assert!(registry.take_instantiation(&ADDRESS).is_none());
     }
 }
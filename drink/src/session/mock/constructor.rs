@@ -0,0 +1,75 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use parity_scale_codec::Encode;
+
+use crate::session::mock::{
+    error::MockingError, CallContext, MessageMock, MockedCallResult, Selector,
+};
+
+/// A mock of contract constructors, used when a contract is instantiated by another contract
+/// (e.g. by a factory).
+///
+/// Constructor mocks are registered for a code hash (see `MockingApi::mock_instantiation`). Every
+/// instantiation of that code is intercepted: if the mock has a constructor for the used selector,
+/// it is executed instead of the real one. Additionally, the mock can decide about the addresses
+/// reported for the new contracts.
+///
+/// Notice that the new contract is still created with the original code, so its messages will be
+/// executed normally (unless mocked separately).
+pub struct ConstructorMock {
+    constructors: BTreeMap<Selector, MessageMock>,
+    addresses: VecDeque<Vec<u8>>,
+}
+
+impl ConstructorMock {
+    /// Creates a new mock without any constructor.
+    pub fn new() -> Self {
+        Self {
+            constructors: BTreeMap::new(),
+            addresses: VecDeque::new(),
+        }
+    }
+
+    /// Adds a constructor mock. Use `mock_message` to create it out of a typed closure - the
+    /// returned value is ignored by the pallet unless the constructor reverts.
+    pub fn with_constructor(mut self, selector: Selector, constructor: MessageMock) -> Self {
+        self.constructors.insert(selector, constructor);
+        self
+    }
+
+    /// Sets the addresses for the subsequent instantiations. The first intercepted instantiation
+    /// gets the first address, and so on. When the addresses run out, the default address
+    /// generation is used.
+    pub fn with_addresses<AccountId: Encode>(
+        mut self,
+        addresses: impl IntoIterator<Item = AccountId>,
+    ) -> Self {
+        self.addresses = addresses.into_iter().map(|a| a.encode()).collect();
+        self
+    }
+
+    /// Try to call a constructor mock. Returns an error if there is no constructor mock for
+    /// `selector`.
+    pub fn call(
+        &mut self,
+        context: &CallContext,
+        selector: Selector,
+        input: Vec<u8>,
+    ) -> MockedCallResult {
+        match self.constructors.get_mut(&selector) {
+            None => Err(MockingError::MessageNotFound(selector)),
+            Some(constructor) => constructor(context, input),
+        }
+    }
+
+    /// Returns the (encoded) address for the next instantiation, if any was set.
+    pub(crate) fn next_address(&mut self) -> Option<Vec<u8>> {
+        self.addresses.pop_front()
+    }
+}
+
+impl Default for ConstructorMock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
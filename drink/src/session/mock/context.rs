@@ -0,0 +1,31 @@
+use parity_scale_codec::Decode;
+
+use crate::Weight;
+
+/// The context in which a mocked message (or constructor) is being called.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallContext {
+    /// The encoded address of the caller: either another contract or the session actor.
+    pub caller: Vec<u8>,
+    /// The number of the block in which the call is executed.
+    pub block_number: u64,
+    /// The value transferred to the mock with the call.
+    ///
+    /// It is read from the transfer event emitted right before the call. Therefore, if the caller
+    /// transferred funds to the mock with a plain transfer and then called it without any value,
+    /// the value of the transfer is reported.
+    pub value: u128,
+    /// The gas left for the call.
+    ///
+    /// The contracts pallet doesn't expose the gas meter of the current frame, so this is the gas
+    /// limit of the whole contract interaction (the session gas limit), i.e. an upper bound of the
+    /// actual gas left.
+    pub gas_left: Weight,
+}
+
+impl CallContext {
+    /// Decodes the caller address.
+    pub fn caller<AccountId: Decode>(&self) -> AccountId {
+        Decode::decode(&mut &*self.caller).expect("Caller address should be decodable")
+    }
+}
@@ -1,10 +1,14 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc};
 
+use contract_transcode::{ContractMessageTranscoder, Tuple, Value};
 use parity_scale_codec::{Decode, Encode};
 
-use crate::{
-    errors::LangError,
-    session::mock::{error::MockingError, MockedCallResult},
+use crate::session::{
+    bundle::ContractBundle,
+    mock::{
+        error::MockingError, spy::ExpectedMessage, CallContext, Expectation, MockOutcome, MockSpy,
+        MockedCall, MockedCallResult,
+    },
 };
 
 /// Alias for a 4-byte selector.
@@ -14,11 +18,74 @@ pub type Selector = [u8; 4];
 /// Notice that in the end, we cannot operate on specific argument/return types. Rust won't let us
 /// have a collection of differently typed closures. Fortunately, we can assume that all types are
 /// en/decodable, so we can use `Vec<u8>` as a common denominator.
-pub type MessageMock = Box<dyn Fn(Vec<u8>) -> MockedCallResult + Send + Sync>;
+///
+/// The mock can keep its own state (it is `FnMut`). To share the state with the test, capture e.g.
+/// an `Arc<Mutex<_>>` in the closure.
+pub type MessageMock = Box<dyn FnMut(&CallContext, Vec<u8>) -> MockedCallResult + Send>;
+
+/// An untyped mock handling all the messages that have no dedicated `MessageMock`. Receives the
+/// call context, the selector and the encoded arguments.
+pub type CatchAllMock = Box<dyn FnMut(&CallContext, Selector, Vec<u8>) -> MockedCallResult + Send>;
+
+/// Describes how a mock handles calls to messages that it doesn't define.
+#[derive(Default)]
+pub enum Fallback {
+    /// Let the contract code handle the call. For mocks deployed with `MockingApi::deploy` this
+    /// results in a trap, while for partially mocked existing contracts the real message is
+    /// executed.
+    #[default]
+    PassThrough,
+    /// End the call with the given outcome.
+    Outcome(MockOutcome),
+    /// Handle the call with the given closure. Its result is interpreted the same way as the result
+    /// of a `MessageMock`.
+    CatchAll(CatchAllMock),
+}
+
+impl Fallback {
+    /// Revert the call with `error`, encoded in the same way as a message returning `Err(error)`
+    /// would do it.
+    pub fn revert_with<E: Encode>(error: E) -> Self {
+        Self::Outcome(MockOutcome::revert_with(error))
+    }
+
+    /// Return `value`, encoded in the same way as a message returning `value` would do it.
+    pub fn return_value<Ret: Encode>(value: Ret) -> Self {
+        Self::Outcome(MockOutcome::return_value(value))
+    }
+
+    /// Trap, as if the contract panicked.
+    pub fn trap() -> Self {
+        Self::Outcome(MockOutcome::Trap)
+    }
+
+    /// Handle the call with a closure receiving the call context, the selector and the encoded
+    /// arguments.
+    pub fn catch_all<
+        Body: FnMut(&CallContext, Selector, Vec<u8>) -> MockedCallResult + Send + 'static,
+    >(
+        body: Body,
+    ) -> Self {
+        Self::CatchAll(Box::new(body))
+    }
+}
+
+/// A message mock operating on decoded values. Receives the message arguments and returns the
+/// message return value (without the implicit `Ok` wrapping done by ink!).
+pub type ValueMessageMock = Box<dyn FnMut(Vec<Value>) -> Value + Send>;
 
 /// A contract mock.
+///
+/// Every call received by the mock is recorded and can be inspected with a `MockSpy` (see
+/// `ContractMock::spy`). Additionally, the mock can hold `Expectation`s about the received calls.
+/// They are checked only on request, with `MockingApi::verify_expectations` (for all the mocks
+/// registered in the session) or `ContractMock::verify_expectations`.
 pub struct ContractMock {
     messages: BTreeMap<Selector, MessageMock>,
+    fallback: Fallback,
+    spy: MockSpy,
+    expectations: Vec<Expectation>,
+    transcoder: Option<Arc<ContractMessageTranscoder>>,
 }
 
 impl ContractMock {
@@ -26,24 +93,190 @@ impl ContractMock {
     pub fn new() -> Self {
         Self {
             messages: BTreeMap::new(),
+            fallback: Fallback::default(),
+            spy: MockSpy::default(),
+            expectations: vec![],
+            transcoder: None,
         }
     }
 
+    /// Creates a new mock without any message, which can be used as a drop-in replacement of the
+    /// contract from `bundle`.
+    ///
+    /// Thanks to the contract metadata, messages can be mocked by name (see
+    /// `ContractMock::with_message_named` and `ContractMock::with_message_values`).
+    pub fn from_bundle(bundle: &ContractBundle) -> Self {
+        let mut mock = Self::new();
+        mock.transcoder = Some(Arc::clone(&bundle.transcoder));
+        mock
+    }
+
     /// Adds a message mock.
     pub fn with_message(mut self, selector: Selector, message: MessageMock) -> Self {
         self.messages.insert(selector, message);
         self
     }
 
+    /// Adds a message mock for the message called `name`. The selector is resolved using the
+    /// contract metadata, so the mock must have been created with `ContractMock::from_bundle`.
+    pub fn with_message_named(
+        self,
+        name: &str,
+        message: MessageMock,
+    ) -> Result<Self, MockingError> {
+        let selector = self.selector(name)?;
+        Ok(self.with_message(selector, message))
+    }
+
+    /// Adds a message mock for the message called `name`, operating on decoded values.
+    ///
+    /// Arguments are decoded and the return value is encoded using the contract metadata, so the
+    /// mock must have been created with `ContractMock::from_bundle`.
+    pub fn with_message_values<Body: FnMut(Vec<Value>) -> Value + Send + 'static>(
+        self,
+        name: &str,
+        body: Body,
+    ) -> Result<Self, MockingError> {
+        let selector = self.selector(name)?;
+        let transcoder = Arc::clone(self.transcoder.as_ref().ok_or(MockingError::NoMetadata)?);
+        let name = name.to_string();
+        let mut body: ValueMessageMock = Box::new(body);
+
+        let message: MessageMock = Box::new(move |_, encoded_input| {
+            let input = [selector.as_slice(), &encoded_input].concat();
+            let args = match transcoder.decode_contract_message(&mut input.as_slice()) {
+                Ok(Value::Map(map)) => map.values().cloned().collect(),
+                Ok(_) => vec![],
+                Err(err) => return Err(MockingError::Transcoding(err.to_string())),
+            };
+            encode_return_value(&transcoder, &name, body(args)).map(MockOutcome::Return)
+        });
+
+        Ok(self.with_message(selector, message))
+    }
+
+    /// Resolves the selector of the message called `name` using the contract metadata.
+    fn selector(&self, name: &str) -> Result<Selector, MockingError> {
+        let transcoder = self.transcoder.as_ref().ok_or(MockingError::NoMetadata)?;
+        transcoder
+            .metadata()
+            .spec()
+            .messages()
+            .iter()
+            .find(|message| message.label() == name)
+            .and_then(|message| message.selector().to_bytes().try_into().ok())
+            .ok_or_else(|| MockingError::UnknownMessage(name.to_string()))
+    }
+
+    /// Sets the policy for calls to messages that are not mocked. By default, such calls are
+    /// passed to the contract code (`Fallback::PassThrough`).
+    pub fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Returns the policy for calls to messages that are not mocked.
+    pub fn fallback(&self) -> &Fallback {
+        &self.fallback
+    }
+
+    /// Adds an expectation about the calls that the mock should receive.
+    pub fn with_expectation(mut self, expectation: Expectation) -> Self {
+        self.expectations.push(expectation);
+        self
+    }
+
+    /// Returns a handle to the calls received by the mock.
+    pub fn spy(&self) -> MockSpy {
+        self.spy.clone()
+    }
+
+    /// Checks all the expectations against the calls received so far.
+    ///
+    /// Expectations about messages referred to by name fail if the mock has no contract metadata or
+    /// if the metadata doesn't contain such a message.
+    pub fn verify_expectations(&self) -> Result<(), MockingError> {
+        let calls = self.spy.calls();
+        self.expectations.iter().try_for_each(|expectation| {
+            if let ExpectedMessage::Named(name) = expectation.message() {
+                self.selector(name)?;
+            }
+            expectation.verify(&calls)
+        })
+    }
+
+    /// Records a call received by the mock. If the contract metadata is available, the message name
+    /// and the decoded arguments are recorded as well.
+    pub(crate) fn record_call(&self, selector: Selector, input: &[u8], context: &CallContext) {
+        let decoded = self.transcoder.as_ref().and_then(|transcoder| {
+            let input = [selector.as_slice(), input].concat();
+            match transcoder.decode_contract_message(&mut input.as_slice()) {
+                Ok(Value::Map(map)) => Some((map.ident(), map.values().cloned().collect())),
+                _ => None,
+            }
+        });
+        let (message, decoded_args) = match decoded {
+            Some((message, args)) => (message, Some(args)),
+            None => (None, None),
+        };
+
+        self.spy.record(MockedCall {
+            selector,
+            input: input.to_vec(),
+            caller: context.caller.clone(),
+            value: context.value,
+            message,
+            decoded_args,
+        });
+    }
+
+    pub(crate) fn fallback_mut(&mut self) -> &mut Fallback {
+        &mut self.fallback
+    }
+
     /// Try to call a message mock. Returns an error if there is no message mock for `selector`.
-    pub fn call(&self, selector: Selector, input: Vec<u8>) -> MockedCallResult {
-        match self.messages.get(&selector) {
+    pub fn call(
+        &mut self,
+        context: &CallContext,
+        selector: Selector,
+        input: Vec<u8>,
+    ) -> MockedCallResult {
+        match self.messages.get_mut(&selector) {
             None => Err(MockingError::MessageNotFound(selector)),
-            Some(message) => message(input),
+            Some(message) => message(context, input),
         }
     }
 }
 
+/// Encodes `value` as the return value of the message `name`, wrapping it in `Ok`, as ink! does.
+fn encode_return_value(
+    transcoder: &ContractMessageTranscoder,
+    name: &str,
+    value: Value,
+) -> Result<Vec<u8>, MockingError> {
+    let metadata = transcoder.metadata();
+    let message = metadata
+        .spec()
+        .messages()
+        .iter()
+        .find(|message| message.label() == name)
+        .ok_or_else(|| MockingError::UnknownMessage(name.to_string()))?;
+
+    let value = Value::Tuple(Tuple::new(Some("Ok"), vec![value]));
+    let mut output = vec![];
+    contract_transcode::TranscoderBuilder::new(metadata.registry())
+        .with_default_custom_type_transcoders()
+        .done()
+        .encode(
+            metadata.registry(),
+            message.return_type().ret_type().ty().id,
+            &value,
+            &mut output,
+        )
+        .map_err(|err| MockingError::Transcoding(err.to_string()))?;
+    Ok(output)
+}
+
 impl Default for ContractMock {
     fn default() -> Self {
         Self::new()
@@ -54,11 +287,37 @@ impl Default for ContractMock {
 ///
 /// In particular, it takes care of decoding the input and encoding the output. Also, wraps the
 /// return value in a `Result`, which is normally done implicitly by ink!.
-pub fn mock_message<Args: Decode, Ret: Encode, Body: Fn(Args) -> Ret + Send + Sync + 'static>(
-    body: Body,
+pub fn mock_message<Args: Decode, Ret: Encode, Body: FnMut(Args) -> Ret + Send + 'static>(
+    mut body: Body,
+) -> MessageMock {
+    mock_message_with_context(move |_: &CallContext, args| body(args))
+}
+
+/// Same as `mock_message`, but the closure additionally receives the context of the call (like the
+/// caller or the current block number).
+pub fn mock_message_with_context<
+    Args: Decode,
+    Ret: Encode,
+    Body: FnMut(&CallContext, Args) -> Ret + Send + 'static,
+>(
+    mut body: Body,
+) -> MessageMock {
+    Box::new(move |context, encoded_input| {
+        let input = Decode::decode(&mut &*encoded_input).map_err(MockingError::ArgumentDecoding)?;
+        Ok(MockOutcome::return_value(body(context, input)))
+    })
+}
+
+/// A helper function to create a message mock out of a typed closure that decides about the whole
+/// outcome of the call (e.g. it can trap or revert with an arbitrary payload).
+pub fn mock_message_with_outcome<
+    Args: Decode,
+    Body: FnMut(&CallContext, Args) -> MockOutcome + Send + 'static,
+>(
+    mut body: Body,
 ) -> MessageMock {
-    Box::new(move |encoded_input| {
+    Box::new(move |context, encoded_input| {
         let input = Decode::decode(&mut &*encoded_input).map_err(MockingError::ArgumentDecoding)?;
-        Ok(Ok::<Ret, LangError>(body(input)).encode())
+        Ok(body(context, input))
     })
 }
@@ -3,10 +3,36 @@ use thiserror::Error;
 use crate::session::mock::Selector;
 
 /// Error type for mocking operations.
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum MockingError {
+    /// The mocked contract has no message with the given selector.
     #[error("Message not found (unknown selector: {0:?})")]
     MessageNotFound(Selector),
+    /// The call input could not be decoded as the message arguments.
     #[error("Decoding message arguments failed: {0:?}")]
     ArgumentDecoding(parity_scale_codec::Error),
+    /// The contract metadata has no message with the given name.
+    #[error("Message '{0}' not found in the contract metadata")]
+    UnknownMessage(String),
+    /// The mock was created without contract metadata, so messages cannot be referred to by name.
+    #[error(
+        "Contract metadata is not available (create the mock with `ContractMock::from_bundle`)"
+    )]
+    NoMetadata,
+    /// Encoding or decoding values with the contract metadata failed.
+    #[error("Transcoding values failed: {0}")]
+    Transcoding(String),
+    /// A message was called a different number of times than expected.
+    #[error(
+        "Expectation not met for {message}: expected {} calls, got {actual}",
+        expected.map_or("at least one".to_string(), |times| times.to_string())
+    )]
+    UnmetExpectation {
+        /// The name of the message.
+        message: String,
+        /// The expected number of calls (`None` means at least one).
+        expected: Option<usize>,
+        /// The actual number of calls.
+        actual: usize,
+    },
 }
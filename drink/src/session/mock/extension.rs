@@ -1,70 +1,217 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
 
+use ink_sandbox::AccountIdFor;
 use parity_scale_codec::{Decode, Encode};
 
 use crate::{
-    errors::MessageResult,
-    pallet_contracts::{chain_extension::ReturnFlags, debug::ExecResult, ExecReturnValue},
-    pallet_contracts_debugging::InterceptingExtT,
-    session::mock::{MockRegistry, Selector},
+    pallet_contracts::{
+        chain_extension::ReturnFlags, debug::ExecResult, Config, Error, ExecReturnValue,
+    },
+    pallet_contracts_debugging::{InterceptingExtT, InterceptionContext, TracingExtT},
+    session::mock::{
+        CallContext, Fallback, MockOutcome, MockRegistry, MockedCallResult, MockingError, Selector,
+    },
+    DispatchError,
 };
 
 /// Runtime extension enabling contract call interception.
-pub(crate) struct MockingExtension<AccountId: Ord> {
+pub(crate) struct MockingExtension<R: Config> {
     /// Mock registry, shared with the sandbox.
     ///
     /// Potentially the runtime is executed in parallel and thus we need to wrap the registry in
     /// `Arc<Mutex>` instead of `Rc<RefCell>`.
-    pub mock_registry: Arc<Mutex<MockRegistry<AccountId>>>,
+    pub mock_registry: Arc<Mutex<MockRegistry<AccountIdFor<R>>>>,
+    _phantom: PhantomData<fn() -> R>,
 }
 
-impl<AccountId: Ord + Decode> InterceptingExtT for MockingExtension<AccountId> {
-    fn intercept_call(
+impl<R: Config> MockingExtension<R> {
+    pub fn new(mock_registry: Arc<Mutex<MockRegistry<AccountIdFor<R>>>>) -> Self {
+        Self {
+            mock_registry,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<R: Config> InterceptingExtT for MockingExtension<R> {
+    fn intercept_call_with_context(
         &self,
         contract_address: Vec<u8>,
-        _is_call: bool,
+        is_call: bool,
         input_data: Vec<u8>,
+        interception_context: InterceptionContext,
     ) -> Vec<u8> {
+        let mut registry = self
+            .mock_registry
+            .lock()
+            .expect("Should be able to acquire registry");
+        let context = CallContext {
+            caller: registry.caller(),
+            block_number: interception_context.block_number,
+            value: interception_context.value,
+            gas_left: registry.gas_limit(),
+        };
+
+        if !is_call {
+            return intercept_constructor::<R>(
+                &mut registry,
+                &context,
+                contract_address,
+                input_data,
+            );
+        }
+
         let contract_address = Decode::decode(&mut &contract_address[..])
             .expect("Contract address should be decodable");
 
-        match self
+        // There is no mock registered for this address (or the input doesn't even contain a
+        // selector), so we return `None` to indicate that the call should be executed normally.
+        let (Some(mock), Some((selector, call_data))) = (
+            registry.find_mut(&contract_address, interception_context.code_hash.as_deref()),
+            split_selector(&input_data),
+        ) else {
+            return None::<()>.encode();
+        };
+
+        mock.record_call(selector, call_data, &context);
+
+        // We intercept the call and return the result of the mock.
+        let result = match mock.call(&context, selector, call_data.to_vec()) {
+            Err(MockingError::MessageNotFound(_)) => match mock.fallback_mut() {
+                Fallback::PassThrough => return None::<()>.encode(),
+                Fallback::Outcome(outcome) => Ok(outcome.clone()),
+                Fallback::CatchAll(body) => body(&context, selector, call_data.to_vec()),
+            },
+            result => result,
+        };
+
+        Some(to_exec_result::<R>(&mut registry, result)).encode()
+    }
+
+    fn intercept_address(
+        &self,
+        _deployer: Vec<u8>,
+        code_hash: Vec<u8>,
+        _input_data: Vec<u8>,
+        _salt: Vec<u8>,
+        address: Vec<u8>,
+    ) -> Vec<u8> {
+        let address = self
             .mock_registry
             .lock()
             .expect("Should be able to acquire registry")
-            .get(&contract_address)
-        {
-            // There is no mock registered for this address, so we return `None` to indicate that
-            // the call should be executed normally.
+            .note_instantiation(code_hash, address);
+
+        match address {
             None => None::<()>.encode(),
-            // We intercept the call and return the result of the mock.
-            Some(mock) => {
-                let (selector, call_data) = input_data.split_at(4);
-                let selector: Selector = selector
-                    .try_into()
-                    .expect("Input data should contain at least selector bytes");
-
-                let result = mock
-                    .call(selector, call_data.to_vec())
-                    .expect("TODO: let the user define the fallback mechanism");
-
-                // Although we don't know the exact type, thanks to the SCALE encoding we know
-                // that `()` will always succeed (we only care about the `Ok`/`Err` distinction).
-                let decoded_result: MessageResult<()> =
-                    Decode::decode(&mut &result[..]).expect("Mock result should be decodable");
-
-                let flags = match decoded_result {
-                    Ok(_) => ReturnFlags::empty(),
-                    Err(_) => ReturnFlags::REVERT,
-                };
-
-                let result: ExecResult = Ok(ExecReturnValue {
-                    flags,
-                    data: result,
-                });
-
-                Some(result).encode()
+            Some(address) => {
+                let address: AccountIdFor<R> = Decode::decode(&mut &address[..])
+                    .expect("Mocked contract address should be decodable");
+                Some(address).encode()
             }
         }
     }
 }
+
+/// Intercepts a constructor call, if the instantiated code is mocked.
+fn intercept_constructor<R: Config>(
+    registry: &mut MockRegistry<AccountIdFor<R>>,
+    context: &CallContext,
+    contract_address: Vec<u8>,
+    input_data: Vec<u8>,
+) -> Vec<u8> {
+    let (Some(mock), Some((selector, call_data))) = (
+        registry.take_instantiation(&contract_address),
+        split_selector(&input_data),
+    ) else {
+        return None::<()>.encode();
+    };
+
+    match mock.call(context, selector, call_data.to_vec()) {
+        // The constructor is not mocked, so we let the real one handle the call.
+        Err(MockingError::MessageNotFound(_)) => None::<()>.encode(),
+        result => Some(to_exec_result::<R>(registry, result)).encode(),
+    }
+}
+
+/// Tracing extension that keeps track of the contract call stack, so that mocks know who called
+/// them.
+pub(crate) struct MockCallStackTracer<AccountId: Ord> {
+    pub mock_registry: Arc<Mutex<MockRegistry<AccountId>>>,
+}
+
+impl<AccountId: Ord> MockCallStackTracer<AccountId> {
+    fn exit(&self) {
+        self.mock_registry
+            .lock()
+            .expect("Should be able to acquire registry")
+            .exit();
+    }
+}
+
+impl<AccountId: Ord> TracingExtT for MockCallStackTracer<AccountId> {
+    fn before_call(&self, contract_address: Vec<u8>, is_call: bool, _input_data: Vec<u8>) {
+        self.mock_registry
+            .lock()
+            .expect("Should be able to acquire registry")
+            .enter(contract_address, is_call);
+    }
+
+    fn after_call(&self, _: Vec<u8>, _: bool, _: Vec<u8>, _: Vec<u8>) {
+        self.exit();
+    }
+
+    fn after_failed_call(&self, _: Vec<u8>, _: bool, _: Vec<u8>) {
+        self.exit();
+    }
+}
+
+/// Converts the result of a mock into the result of the contract execution.
+///
+/// A failed mock traps the contract, just like a failing real message would. The error is recorded
+/// in `registry`, so that it can be reported by `MockingApi::verify_expectations`.
+fn to_exec_result<R: Config>(
+    registry: &mut MockRegistry<AccountIdFor<R>>,
+    result: MockedCallResult,
+) -> ExecResult {
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(error) => {
+            registry.record_error(error);
+            return callee_error(Error::<R>::ContractTrapped.into());
+        }
+    };
+
+    let (flags, data) = match outcome {
+        MockOutcome::Return(data) => (ReturnFlags::empty(), data),
+        MockOutcome::Revert(data) => (ReturnFlags::REVERT, data),
+        MockOutcome::Trap => return callee_error(Error::<R>::ContractTrapped.into()),
+        MockOutcome::OutOfGas => return callee_error(Error::<R>::OutOfGas.into()),
+        MockOutcome::DispatchError(error) => return callee_error(error),
+    };
+
+    Ok(ExecReturnValue { flags, data })
+}
+
+/// Builds a failed `ExecResult` with the error raised by the called contract (so that the caller
+/// observes `CalleeTrapped`, like for a real contract failure).
+fn callee_error(error: DispatchError) -> ExecResult {
+    let mut result: ExecResult = Err(error.into());
+    if let Err(exec_error) = &mut result {
+        // `pallet_contracts` doesn't export `ErrorOrigin` (its errors convert with the `Caller`
+        // origin), so we take the `Callee` variant from its encoding.
+        exec_error.origin =
+            Decode::decode(&mut &[1u8][..]).expect("`ErrorOrigin::Callee` should be decodable");
+    }
+    result
+}
+
+/// Splits the call input into the selector and the encoded arguments. Returns `None` if the input
+/// is too short to contain a selector.
+fn split_selector(input_data: &[u8]) -> Option<(Selector, &[u8])> {
+    let (selector, call_data) = input_data.split_first_chunk::<4>()?;
+    Some((*selector, call_data))
+}
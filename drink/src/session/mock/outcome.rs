@@ -0,0 +1,78 @@
+use parity_scale_codec::{Decode, Encode};
+
+use crate::{
+    errors::{LangError, MessageResult},
+    DispatchError,
+};
+
+/// The outcome of a mocked call. Allows simulating every way in which a contract call can end.
+///
+/// Mocked calls don't consume any gas and there is deliberately no outcome charging some: the
+/// contracts pallet doesn't let call interceptors access the gas meter (the interceptor result
+/// carries no gas information), so such an outcome couldn't be honored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockOutcome {
+    /// Successfully return the given (already encoded) payload.
+    Return(Vec<u8>),
+    /// Revert with the given (already encoded) payload.
+    Revert(Vec<u8>),
+    /// Trap, as if the contract panicked.
+    Trap,
+    /// Fail with the given dispatch error, as if it was raised by the called contract.
+    DispatchError(DispatchError),
+    /// Fail as if the called contract ran out of gas.
+    ///
+    /// Note: the call interceptor cannot charge gas, so no gas is actually consumed - the caller
+    /// only observes the failure.
+    OutOfGas,
+}
+
+impl MockOutcome {
+    /// Return `value`, encoded in the same way as a message returning `value` would do it.
+    pub fn return_value<Ret: Encode>(value: Ret) -> Self {
+        Self::Return(Ok::<Ret, LangError>(value).encode())
+    }
+
+    /// Revert with `error`, encoded in the same way as a message returning `Err(error)` would do
+    /// it.
+    pub fn revert_with<E: Encode>(error: E) -> Self {
+        Self::Revert(Ok::<Result<(), E>, LangError>(Err(error)).encode())
+    }
+}
+
+/// Interprets raw output of a message: if it is an encoded `Err(LangError)`, the call is reverted,
+/// if it is not an encoded `MessageResult` at all, the call traps, otherwise the output is
+/// returned.
+impl From<Vec<u8>> for MockOutcome {
+    fn from(output: Vec<u8>) -> Self {
+        // Although we don't know the exact type, thanks to the SCALE encoding we know
+        // that `()` will always succeed (we only care about the `Ok`/`Err` distinction).
+        match MessageResult::<()>::decode(&mut &output[..]) {
+            Ok(Ok(_)) => Self::Return(output),
+            Ok(Err(_)) => Self::Revert(output),
+            Err(_) => Self::Trap,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_output_is_interpreted_by_message_result() {
+        let returned = Ok::<u32, LangError>(7).encode();
+        let reverted = Err::<(), LangError>(LangError::CouldNotReadInput).encode();
+
+        assert_eq!(
+            MockOutcome::from(returned.clone()),
+            MockOutcome::Return(returned)
+        );
+        assert_eq!(
+            MockOutcome::from(reverted.clone()),
+            MockOutcome::Revert(reverted)
+        );
+        assert_eq!(MockOutcome::from(vec![]), MockOutcome::Trap);
+        assert_eq!(MockOutcome::from(vec![7]), MockOutcome::Trap);
+    }
+}
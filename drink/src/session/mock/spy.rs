@@ -0,0 +1,260 @@
+use std::{
+    fmt::{Display, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use contract_transcode::Value;
+use parity_scale_codec::{Decode, Encode};
+
+use crate::session::mock::{error::MockingError, Selector};
+
+/// A single call to a mocked contract, as observed by the mocking extension.
+///
+/// Similarly to message mocks, the call is stored in the encoded form. Use `args` and `caller` to
+/// decode the interesting parts. If the mock was created with `ContractMock::from_bundle`, the
+/// message name and the decoded arguments are recorded as well.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockedCall {
+    /// The selector of the called message.
+    pub selector: Selector,
+    /// The encoded arguments (i.e. the input without the selector).
+    pub input: Vec<u8>,
+    /// The encoded address of the caller: either another contract or the session actor.
+    pub caller: Vec<u8>,
+    /// The value transferred with the call (see `CallContext::value`).
+    pub value: u128,
+    /// The name of the called message, resolved with the contract metadata.
+    pub message: Option<String>,
+    /// The arguments decoded with the contract metadata.
+    pub decoded_args: Option<Vec<Value>>,
+}
+
+impl MockedCall {
+    /// Decodes the call arguments.
+    pub fn args<Args: Decode>(&self) -> Result<Args, MockingError> {
+        Decode::decode(&mut &*self.input).map_err(MockingError::ArgumentDecoding)
+    }
+
+    /// Decodes the caller address.
+    pub fn caller<AccountId: Decode>(&self) -> AccountId {
+        Decode::decode(&mut &*self.caller).expect("Caller address should be decodable")
+    }
+}
+
+/// A handle to the calls received by a `ContractMock`.
+///
+/// The spy remains valid after the mock has been moved into the session, so that the test can
+/// inspect the calls at any point.
+#[derive(Clone, Default)]
+pub struct MockSpy {
+    calls: Arc<Mutex<Vec<MockedCall>>>,
+}
+
+impl MockSpy {
+    /// Returns all the calls received so far, in the order of execution.
+    pub fn calls(&self) -> Vec<MockedCall> {
+        self.calls
+            .lock()
+            .expect("Should be able to acquire mocked calls")
+            .clone()
+    }
+
+    /// Returns all the calls of the message with `selector` received so far.
+    pub fn calls_to(&self, selector: Selector) -> Vec<MockedCall> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.selector == selector)
+            .collect()
+    }
+
+    /// Returns all the calls of the message called `name` received so far. Only calls recorded by
+    /// mocks created with `ContractMock::from_bundle` carry the message name.
+    pub fn calls_to_message(&self, name: &str) -> Vec<MockedCall> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.message.as_deref() == Some(name))
+            .collect()
+    }
+
+    /// Returns the number of calls of the message with `selector` received so far.
+    pub fn call_count(&self, selector: Selector) -> usize {
+        self.calls_to(selector).len()
+    }
+
+    pub(crate) fn record(&self, call: MockedCall) {
+        self.calls
+            .lock()
+            .expect("Should be able to acquire mocked calls")
+            .push(call);
+    }
+}
+
+/// The message that an `Expectation` is about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ExpectedMessage {
+    Selector(Selector),
+    Named(String),
+}
+
+impl ExpectedMessage {
+    fn matches(&self, call: &MockedCall) -> bool {
+        match self {
+            Self::Selector(selector) => call.selector == *selector,
+            Self::Named(name) => call.message.as_deref() == Some(name.as_str()),
+        }
+    }
+}
+
+impl Display for ExpectedMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Selector(selector) => write!(f, "selector {selector:?}"),
+            Self::Named(name) => write!(f, "message '{name}'"),
+        }
+    }
+}
+
+/// An expectation about calls to a mocked message.
+///
+/// By default, the message is expected to be called at least once, with any arguments.
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    message: ExpectedMessage,
+    times: Option<usize>,
+    args: Option<Vec<u8>>,
+    values: Option<Vec<Value>>,
+}
+
+impl Expectation {
+    /// Expect the message with `selector` to be called.
+    pub fn called(selector: Selector) -> Self {
+        Self::new(ExpectedMessage::Selector(selector))
+    }
+
+    /// Expect the message called `name` to be called. The name is resolved with the contract
+    /// metadata, so the expectation must be added to a mock created with
+    /// `ContractMock::from_bundle`.
+    pub fn called_message(name: &str) -> Self {
+        Self::new(ExpectedMessage::Named(name.to_string()))
+    }
+
+    fn new(message: ExpectedMessage) -> Self {
+        Self {
+            message,
+            times: None,
+            args: None,
+            values: None,
+        }
+    }
+
+    /// Expect exactly `times` matching calls.
+    pub fn times(self, times: usize) -> Self {
+        Self {
+            times: Some(times),
+            ..self
+        }
+    }
+
+    /// Take into account only calls with exactly `args` as the arguments.
+    pub fn with<Args: Encode>(self, args: Args) -> Self {
+        Self {
+            args: Some(args.encode()),
+            ..self
+        }
+    }
+
+    /// Take into account only calls with exactly `values` as the decoded arguments. Requires the
+    /// contract metadata, like `Expectation::called_message`.
+    pub fn with_values(self, values: Vec<Value>) -> Self {
+        Self {
+            values: Some(values),
+            ..self
+        }
+    }
+
+    pub(crate) fn message(&self) -> &ExpectedMessage {
+        &self.message
+    }
+
+    /// Checks the expectation against `calls`.
+    pub fn verify(&self, calls: &[MockedCall]) -> Result<(), MockingError> {
+        let actual = calls
+            .iter()
+            .filter(|call| self.message.matches(call))
+            .filter(|call| self.args.as_ref().map_or(true, |args| *args == call.input))
+            .filter(|call| {
+                self.values
+                    .as_ref()
+                    .map_or(true, |values| Some(values) == call.decoded_args.as_ref())
+            })
+            .count();
+
+        let met = match self.times {
+            Some(times) => actual == times,
+            None => actual > 0,
+        };
+
+        match met {
+            true => Ok(()),
+            false => Err(MockingError::UnmetExpectation {
+                message: self.message.to_string(),
+                expected: self.times,
+                actual,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SELECTOR: Selector = [1, 2, 3, 4];
+
+    fn call(args: u32) -> MockedCall {
+        MockedCall {
+            selector: SELECTOR,
+            input: args.encode(),
+            caller: vec![],
+            value: 0,
+            message: Some("transfer".to_string()),
+            decoded_args: Some(vec![Value::UInt(args as u128)]),
+        }
+    }
+
+    #[test]
+    fn expectation_counts_only_matching_calls() {
+        let calls = [call(1), call(2), call(1)];
+
+        assert!(Expectation::called(SELECTOR).verify(&calls).is_ok());
+        assert!(Expectation::called(SELECTOR)
+            .times(2)
+            .with(1u32)
+            .verify(&calls)
+            .is_ok());
+        assert!(matches!(
+            Expectation::called(SELECTOR).times(2).verify(&calls),
+            Err(MockingError::UnmetExpectation { actual: 3, .. })
+        ));
+        assert!(Expectation::called([0; 4]).verify(&calls).is_err());
+    }
+
+    #[test]
+    fn named_expectation_matches_decoded_calls() {
+        let calls = [call(1), call(2)];
+
+        assert!(Expectation::called_message("transfer")
+            .times(2)
+            .verify(&calls)
+            .is_ok());
+        assert!(Expectation::called_message("transfer")
+            .with_values(vec![Value::UInt(2)])
+            .times(1)
+            .verify(&calls)
+            .is_ok());
+        assert!(matches!(
+            Expectation::called_message("approve").verify(&calls),
+            Err(MockingError::UnmetExpectation { actual: 0, .. })
+        ));
+    }
+}
@@ -1,10 +1,11 @@
 //! Mocking API for the sandbox.
 use ink_sandbox::{api::prelude::*, AccountIdFor, Sandbox};
+use parity_scale_codec::Encode;
 
-use super::Session;
+use super::{HashFor, Session};
 use crate::{
-    pallet_contracts::Config,
-    session::mock::ContractMock,
+    pallet_contracts::{self, Config},
+    session::mock::{ConstructorMock, ContractMock, MockingError},
     // DEFAULT_GAS_LIMIT,
 };
 
@@ -15,7 +16,36 @@ pub trait MockingApi<R: Config> {
 
     /// Mock part of an existing contract. In particular, allows to override real behavior of
     /// deployed contract's messages.
-    fn mock_existing_contract(&mut self, _mock: ContractMock, _address: AccountIdFor<R>);
+    ///
+    /// Only the messages defined in `mock` are overridden - calls with any other selector are
+    /// executed by the real contract code. Registering a mock for an address that is already
+    /// mocked replaces the previous mock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no contract deployed at `address`.
+    fn mock_existing_contract(&mut self, mock: ContractMock, address: AccountIdFor<R>);
+
+    /// Mock all the contracts with the code `code_hash`: both the existing ones and the ones that
+    /// will be deployed later (also by other contracts). Similarly to `mock_existing_contract`,
+    /// only the messages defined in `mock` are overridden.
+    ///
+    /// All the instances share `mock` (including its state, spy and expectations). A mock
+    /// registered for a particular address (with `mock_existing_contract` or `deploy`) takes
+    /// precedence.
+    fn mock_code_hash(&mut self, code_hash: HashFor<R>, mock: ContractMock);
+
+    /// Mock constructors of the contract code with `code_hash`. Every subsequent instantiation of
+    /// this code (also the ones performed by other contracts) will be handled by `mock`.
+    ///
+    /// Requires the sandbox runtime to use `DrinkAddressGenerator` (which is the case for the
+    /// sandboxes created with `drink::create_sandbox!`).
+    fn mock_instantiation(&mut self, code_hash: HashFor<R>, mock: ConstructorMock);
+
+    /// Checks the expectations of all the registered mocks against the calls they have received so
+    /// far. Returns the first error raised by a mock during contract execution (such a call traps)
+    /// or, if there was none, the first unmet expectation.
+    fn verify_expectations(&self) -> Result<(), MockingError>;
 }
 
 impl<T: Sandbox> MockingApi<T::Runtime> for Session<T>
@@ -56,17 +86,225 @@ where
         mock_address
     }
 
-    fn mock_existing_contract(&mut self, _mock: ContractMock, _address: AccountIdFor<T::Runtime>) {
-        todo!("soon")
+    fn mock_existing_contract(&mut self, mock: ContractMock, address: AccountIdFor<T::Runtime>) {
+        let is_contract = self
+            .sandbox()
+            .execute_with(|| pallet_contracts::Pallet::<T::Runtime>::code_hash(&address))
+            .is_some();
+        assert!(
+            is_contract,
+            "There is no contract deployed at the given address"
+        );
+
+        self.mocks
+            .lock()
+            .expect("Should be able to acquire lock on registry")
+            .register(address, mock);
+    }
+
+    fn mock_code_hash(&mut self, code_hash: HashFor<T::Runtime>, mock: ContractMock) {
+        self.mocks
+            .lock()
+            .expect("Should be able to acquire lock on registry")
+            .register_code(code_hash.encode(), mock);
+    }
+
+    fn mock_instantiation(&mut self, code_hash: HashFor<T::Runtime>, mock: ConstructorMock) {
+        self.mocks
+            .lock()
+            .expect("Should be able to acquire lock on registry")
+            .register_constructor(code_hash.encode(), mock);
+    }
+
+    fn verify_expectations(&self) -> Result<(), MockingError> {
+        let registry = self
+            .mocks
+            .lock()
+            .expect("Should be able to acquire lock on registry");
+        if let Some(error) = registry.errors().first() {
+            return Err(error.clone());
+        }
+        let result = registry
+            .mocks()
+            .try_for_each(ContractMock::verify_expectations);
+        result
     }
 }
 
 /// A dummy contract that is used to deploy a mock.
 ///
-/// Has a single noop constructor and a single panicking message.
+/// Has a single noop constructor and a single panicking message. Thus, calling a message that is
+/// not mocked results in a trap.
 const DUMMY_CONTRACT: &str = r#"
 (module
 	(import "env" "memory" (memory 1 1))
 	(func (export "deploy"))
 	(func (export "call") (unreachable))
 )"#;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ink_sandbox::{api::prelude::*, AccountIdFor, Sandbox};
+
+    use super::MockingApi;
+    use crate::{
+        minimal::{MinimalSandbox, MinimalSandboxRuntime},
+        pallet_contracts::{Determinism, ExecReturnValue},
+        session::{
+            bundle::tests::truthful,
+            mock::{CallContext, ContractMock, MockOutcome, MockingError, Selector},
+            Session,
+        },
+        DispatchError, Weight,
+    };
+
+    const SELECTOR: Selector = [1, 2, 3, 4];
+
+    fn call(
+        session: &mut Session<MinimalSandbox>,
+        address: AccountIdFor<MinimalSandboxRuntime>,
+        value: u128,
+    ) -> Result<ExecReturnValue, DispatchError> {
+        let gas_limit = session.get_gas_limit();
+        session
+            .sandbox()
+            .call_contract(
+                address,
+                value,
+                SELECTOR.to_vec(),
+                MinimalSandbox::default_actor(),
+                gas_limit,
+                None,
+                Determinism::Enforced,
+            )
+            .result
+    }
+
+    #[test]
+    fn failed_mock_traps_and_is_reported() {
+        let mut session = Session::<MinimalSandbox>::default();
+        let mock = ContractMock::new().with_message(
+            SELECTOR,
+            Box::new(|_, _| Err(MockingError::UnknownMessage("flip".to_string()))),
+        );
+        let address = session.mocking_api().deploy(mock);
+
+        assert!(call(&mut session, address, 0).is_err());
+        assert!(matches!(
+            session.mocking_api().verify_expectations(),
+            Err(MockingError::UnknownMessage(message)) if message == "flip"
+        ));
+    }
+
+    #[test]
+    fn mock_receives_transferred_value_and_gas() {
+        let mut session = Session::<MinimalSandbox>::default();
+        let gas_limit = Weight::from_parts(50_000_000_000, 1024 * 1024);
+        session.set_gas_limit(gas_limit);
+
+        let contexts = Arc::new(Mutex::new(Vec::<CallContext>::new()));
+        let recorded = Arc::clone(&contexts);
+        let mock = ContractMock::new().with_message(
+            SELECTOR,
+            Box::new(move |context, _| {
+                recorded.lock().unwrap().push(context.clone());
+                Ok(MockOutcome::return_value(()))
+            }),
+        );
+        let spy = mock.spy();
+        let address = session.mocking_api().deploy(mock);
+
+        assert!(call(&mut session, address.clone(), 1_000).is_ok());
+        assert!(call(&mut session, address, 0).is_ok());
+
+        assert_eq!(
+            spy.calls()
+                .iter()
+                .map(|call| call.value)
+                .collect::<Vec<_>>(),
+            vec![1_000, 0]
+        );
+
+        let contexts = contexts.lock().unwrap();
+        assert_eq!(
+            contexts
+                .iter()
+                .map(|context| context.value)
+                .collect::<Vec<_>>(),
+            vec![1_000, 0]
+        );
+        assert_eq!(contexts[0].gas_left, gas_limit);
+        assert_eq!(
+            contexts[0].caller::<AccountIdFor<MinimalSandboxRuntime>>(),
+            MinimalSandbox::default_actor()
+        );
+    }
+
+    #[test]
+    fn code_hash_mock_applies_to_all_instances() {
+        let mut session = Session::<MinimalSandbox>::default();
+        let wasm = truthful().wasm;
+        let code_hash = session.upload(wasm.clone()).unwrap();
+        let instantiate = |session: &mut Session<MinimalSandbox>, salt: u8| {
+            session
+                .sandbox()
+                .deploy_contract(
+                    wasm.clone(),
+                    0,
+                    vec![],
+                    vec![salt],
+                    MinimalSandbox::default_actor(),
+                    MinimalSandbox::default_gas_limit(),
+                    None,
+                )
+                .result
+                .unwrap()
+                .account_id
+        };
+
+        let existing = instantiate(&mut session, 0);
+        session.mocking_api().mock_code_hash(
+            code_hash,
+            ContractMock::new().with_message(
+                SELECTOR,
+                Box::new(|_, _| Ok(MockOutcome::return_value(7u8))),
+            ),
+        );
+        let later = instantiate(&mut session, 1);
+        let overridden = instantiate(&mut session, 2);
+        session.mocking_api().mock_existing_contract(
+            ContractMock::new().with_message(
+                SELECTOR,
+                Box::new(|_, _| Ok(MockOutcome::return_value(8u8))),
+            ),
+            overridden.clone(),
+        );
+
+        // The mocked output is the encoded `Ok(value)`.
+        assert_eq!(
+            call(&mut session, existing.clone(), 0).unwrap().data,
+            [0, 7]
+        );
+        assert_eq!(call(&mut session, later, 0).unwrap().data, [0, 7]);
+        assert_eq!(call(&mut session, overridden, 0).unwrap().data, [0, 8]);
+
+        // Messages that are not mocked reach the real code.
+        let gas_limit = session.get_gas_limit();
+        let output = session
+            .sandbox()
+            .call_contract(
+                existing,
+                0,
+                vec![9; 4],
+                MinimalSandbox::default_actor(),
+                gas_limit,
+                None,
+                Determinism::Enforced,
+            )
+            .result
+            .unwrap();
+        assert_eq!(output.data, [0, 1]);
+    }
+}
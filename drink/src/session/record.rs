@@ -8,12 +8,18 @@ use parity_scale_codec::{Decode, Encode};
 use crate::{
     errors::MessageResult,
     minimal::{MinimalSandboxRuntime, RuntimeEvent},
-    session::{error::SessionError, BalanceOf},
+    runtime_call::DispatchedRuntimeCall,
+    session::{
+        error::SessionError,
+        trace::{CallTrace, ChainExtensionCall},
+        BalanceOf,
+    },
 };
 
 type ContractInstantiateResult<R> =
     pallet_contracts::ContractInstantiateResult<AccountIdFor<R>, BalanceOf<R>, EventRecordOf<R>>;
 type ContractExecResult<R> = pallet_contracts::ContractExecResult<BalanceOf<R>, EventRecordOf<R>>;
+type RuntimeCallOf<R> = <R as SysConfig>::RuntimeCall;
 
 /// Data structure storing the results of contract interaction during a session.
 ///
@@ -37,6 +43,18 @@ pub struct Record<Config: pallet_contracts::Config> {
 
     /// The events emitted by the contracts.
     event_batches: Vec<EventBatch<Config>>,
+
+    /// The debug messages emitted by the contracts, one entry per contract interaction.
+    debug_messages: Vec<Vec<String>>,
+
+    /// The traces of contract interactions (recorded only if call tracing is enabled).
+    call_traces: Vec<CallTrace<AccountIdFor<Config>>>,
+
+    /// The chain extension calls made by the contracts, in the order of execution.
+    chain_extension_calls: Vec<ChainExtensionCall<AccountIdFor<Config>>>,
+
+    /// The runtime calls that the contracts tried to dispatch, in the order of execution.
+    runtime_calls: Vec<DispatchedRuntimeCall<RuntimeCallOf<Config>>>,
 }
 
 // API for `Session` to record results and events related to contract interaction.
@@ -60,6 +78,28 @@ impl<Config: pallet_contracts::Config> Record<Config> {
     pub(super) fn push_event_batches(&mut self, events: Vec<EventRecordOf<Config>>) {
         self.event_batches.push(EventBatch { events });
     }
+
+    pub(super) fn push_debug_messages(&mut self, messages: Vec<String>) {
+        self.debug_messages.push(messages);
+    }
+
+    pub(super) fn push_call_traces(&mut self, traces: Vec<CallTrace<AccountIdFor<Config>>>) {
+        self.call_traces.extend(traces);
+    }
+
+    pub(super) fn push_chain_extension_calls(
+        &mut self,
+        calls: Vec<ChainExtensionCall<AccountIdFor<Config>>>,
+    ) {
+        self.chain_extension_calls.extend(calls);
+    }
+
+    pub(super) fn push_runtime_calls(
+        &mut self,
+        calls: Vec<DispatchedRuntimeCall<RuntimeCallOf<Config>>>,
+    ) {
+        self.runtime_calls.extend(calls);
+    }
 }
 
 // API for the end user.
@@ -130,6 +170,53 @@ impl<Config: pallet_contracts::Config> Record<Config> {
     pub fn last_event_batch(&self) -> &EventBatch<Config> {
         self.event_batches.last().expect("No event batches")
     }
+
+    /// Returns the debug messages (e.g. printed with ink!'s `debug_println!`) of all the contract
+    /// interactions (deployments and calls) that happened during the session. Every entry
+    /// corresponds to a single interaction and contains messages from all the contracts involved.
+    pub fn debug_messages(&self) -> &[Vec<String>] {
+        &self.debug_messages
+    }
+
+    /// Returns the debug messages of the last contract interaction that happened during the
+    /// session. Panics if there were no contract interactions.
+    pub fn last_debug_messages(&self) -> &[String] {
+        self.debug_messages.last().expect("No debug messages")
+    }
+
+    /// Returns the traces of all the contract interactions (deployments and calls) that happened
+    /// during the session while call tracing was enabled.
+    pub fn call_traces(&self) -> &[CallTrace<AccountIdFor<Config>>] {
+        &self.call_traces
+    }
+
+    /// Returns the trace of the last contract interaction that happened during the session while
+    /// call tracing was enabled. Panics if there were no traced interactions.
+    pub fn last_call_trace(&self) -> &CallTrace<AccountIdFor<Config>> {
+        self.call_traces.last().expect("No call traces")
+    }
+
+    /// Returns all the chain extension calls made by the contracts during the session.
+    ///
+    /// Only calls handled by chain extensions that report them (like `DrinkChainExtension`, used by
+    /// `MinimalSandbox`) are recorded.
+    pub fn chain_extension_calls(&self) -> &[ChainExtensionCall<AccountIdFor<Config>>] {
+        &self.chain_extension_calls
+    }
+
+    /// Returns the last chain extension call made by the contracts during the session. Panics if
+    /// there were no recorded chain extension calls.
+    pub fn last_chain_extension_call(&self) -> &ChainExtensionCall<AccountIdFor<Config>> {
+        self.chain_extension_calls
+            .last()
+            .expect("No chain extension calls")
+    }
+
+    /// Returns all the runtime calls that the contracts tried to dispatch (with `call_runtime`)
+    /// during the session, including the ones rejected by the filter and the mocked ones.
+    pub fn runtime_calls(&self) -> &[DispatchedRuntimeCall<RuntimeCallOf<Config>>] {
+        &self.runtime_calls
+    }
 }
 
 /// A batch of runtime events that were emitted during a single contract interaction.
@@ -0,0 +1,323 @@
+//! Recording session interactions to replay files and replaying them.
+//!
+//! When replay recording is enabled (`Session::set_replay_recording`), every contract deployment
+//! and call is stored as a `ReplayStep`, together with its outcome and emitted events. The log can
+//! be saved to a (JSON) file and replayed later against a fresh session with `Session::replay`,
+//! which fails on the first step whose outcome or events differ from the recorded ones.
+
+use std::{fs, path::Path};
+
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{hex, session::error::SessionError};
+
+/// Version of the replay file format.
+const REPLAY_FILE_VERSION: u64 = 1;
+
+/// The kind of a recorded interaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepKind {
+    /// Contract deployment.
+    Deploy {
+        /// The path of the bundle that the contract was deployed from. `None` if the contract was
+        /// deployed from raw code, in which case the step cannot be replayed.
+        bundle: Option<String>,
+        /// The constructor name.
+        constructor: String,
+        /// The salt.
+        salt: Vec<u8>,
+    },
+    /// Contract call.
+    Call {
+        /// The encoded address of the called contract.
+        address: Vec<u8>,
+        /// The message name.
+        message: String,
+    },
+}
+
+/// A single recorded interaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayStep {
+    /// What was done.
+    pub kind: StepKind,
+    /// The encoded account that performed the interaction.
+    pub actor: Vec<u8>,
+    /// The arguments of the constructor or message.
+    pub args: Vec<String>,
+    /// The encoded endowment (`Option<Balance>`).
+    pub value: Vec<u8>,
+    /// The block number at which the interaction was performed.
+    pub block: u64,
+    /// The outcome: the encoded address of the deployed contract or the raw call output in case of
+    /// success, the error description otherwise.
+    pub outcome: Result<Vec<u8>, String>,
+    /// The encoded events emitted during the interaction.
+    pub events: Vec<Vec<u8>>,
+}
+
+/// An ordered log of recorded interactions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayLog {
+    steps: Vec<ReplayStep>,
+}
+
+/// Errors that can occur while loading or replaying a replay log.
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    /// The replay file is malformed.
+    #[error("Invalid replay file: {0}")]
+    Format(String),
+    /// Setting up the interaction failed (e.g. the bundle couldn't be loaded).
+    #[error("Step {step}: {error}")]
+    Session {
+        /// The index of the failed step.
+        step: usize,
+        /// The underlying error.
+        error: SessionError,
+    },
+    /// The contract was not deployed from a bundle file, so it cannot be deployed again.
+    #[error("Step {step}: the contract was not deployed from a bundle file")]
+    UnknownBundle {
+        /// The index of the failed step.
+        step: usize,
+    },
+    /// The replayed interaction behaved differently than the recorded one.
+    #[error("Step {step}: {what} mismatch\n  expected: {expected}\n  actual:   {actual}")]
+    Mismatch {
+        /// The index of the failed step.
+        step: usize,
+        /// What differs (`outcome` or `events`).
+        what: &'static str,
+        /// The recorded value.
+        expected: String,
+        /// The replayed value.
+        actual: String,
+    },
+}
+
+impl ReplayLog {
+    /// Returns the recorded steps.
+    pub fn steps(&self) -> &[ReplayStep] {
+        &self.steps
+    }
+
+    pub(crate) fn push(&mut self, step: ReplayStep) {
+        self.steps.push(step);
+    }
+
+    /// Sets the bundle path of the last step, if it is a deployment.
+    pub(crate) fn set_last_bundle(&mut self, path: String) {
+        if let Some(ReplayStep {
+            kind: StepKind::Deploy { bundle, .. },
+            ..
+        }) = self.steps.last_mut()
+        {
+            *bundle = Some(path);
+        }
+    }
+
+    /// Saves the log in the file under `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let steps = self.steps.iter().map(step_to_json).collect::<Vec<_>>();
+        let content = json!({ "version": REPLAY_FILE_VERSION, "steps": steps });
+        let content = serde_json::to_string_pretty(&content)
+            .map_err(|err| ReplayError::Format(err.to_string()))?;
+        fs::write(&path, content).map_err(|err| {
+            ReplayError::Format(format!(
+                "Failed to write {}: {err}",
+                path.as_ref().display()
+            ))
+        })
+    }
+
+    /// Loads the log from the file under `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let content = fs::read_to_string(&path).map_err(|err| {
+            ReplayError::Format(format!("Failed to read {}: {err}", path.as_ref().display()))
+        })?;
+        let content: Value =
+            serde_json::from_str(&content).map_err(|err| ReplayError::Format(err.to_string()))?;
+
+        match content.get("version").and_then(Value::as_u64) {
+            Some(REPLAY_FILE_VERSION) => {}
+            version => {
+                return Err(ReplayError::Format(format!(
+                    "Unsupported version: {version:?}"
+                )))
+            }
+        }
+
+        let steps = content
+            .get("steps")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ReplayError::Format("Missing `steps`".to_string()))?
+            .iter()
+            .map(step_from_json)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { steps })
+    }
+}
+
+fn step_to_json(step: &ReplayStep) -> Value {
+    let mut value = match &step.kind {
+        StepKind::Deploy {
+            bundle,
+            constructor,
+            salt,
+        } => json!({
+            "deploy": { "bundle": bundle, "constructor": constructor, "salt": hex::encode(salt) }
+        }),
+        StepKind::Call { address, message } => json!({
+            "call": { "address": hex::encode(address), "message": message }
+        }),
+    };
+
+    value["actor"] = json!(hex::encode(&step.actor));
+    value["args"] = json!(step.args);
+    value["value"] = json!(hex::encode(&step.value));
+    value["block"] = json!(step.block);
+    value["outcome"] = match &step.outcome {
+        Ok(output) => json!({ "ok": hex::encode(output) }),
+        Err(error) => json!({ "err": error }),
+    };
+    value["events"] = json!(step
+        .events
+        .iter()
+        .map(|e| hex::encode(e))
+        .collect::<Vec<_>>());
+    value
+}
+
+fn step_from_json(value: &Value) -> Result<ReplayStep, ReplayError> {
+    let kind = match (value.get("deploy"), value.get("call")) {
+        (Some(deploy), _) => StepKind::Deploy {
+            bundle: deploy
+                .get("bundle")
+                .and_then(Value::as_str)
+                .map(String::from),
+            constructor: string_field(deploy, "constructor")?,
+            salt: hex_field(deploy, "salt")?,
+        },
+        (None, Some(call)) => StepKind::Call {
+            address: hex_field(call, "address")?,
+            message: string_field(call, "message")?,
+        },
+        (None, None) => return Err(ReplayError::Format("Unknown step kind".to_string())),
+    };
+
+    let outcome = value
+        .get("outcome")
+        .ok_or_else(|| ReplayError::Format("Missing `outcome`".to_string()))?;
+    let outcome = match outcome.get("err") {
+        Some(_) => Err(string_field(outcome, "err")?),
+        None => Ok(hex_field(outcome, "ok")?),
+    };
+
+    Ok(ReplayStep {
+        kind,
+        actor: hex_field(value, "actor")?,
+        args: value
+            .get("args")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ReplayError::Format("Missing `args`".to_string()))?
+            .iter()
+            .map(|arg| arg.as_str().map(String::from))
+            .collect::<Option<_>>()
+            .ok_or_else(|| ReplayError::Format("Arguments should be strings".to_string()))?,
+        value: hex_field(value, "value")?,
+        block: value
+            .get("block")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| ReplayError::Format("Missing `block`".to_string()))?,
+        outcome,
+        events: value
+            .get("events")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ReplayError::Format("Missing `events`".to_string()))?
+            .iter()
+            .map(|event| event.as_str().and_then(hex::decode))
+            .collect::<Option<_>>()
+            .ok_or_else(|| ReplayError::Format("Events should be hex strings".to_string()))?,
+    })
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String, ReplayError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .map(String::from)
+        .ok_or_else(|| ReplayError::Format(format!("Missing `{field}`")))
+}
+
+fn hex_field(value: &Value, field: &str) -> Result<Vec<u8>, ReplayError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .and_then(hex::decode)
+        .ok_or_else(|| ReplayError::Format(format!("Missing or invalid `{field}`")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        minimal::MinimalSandbox,
+        session::{bundle::tests::save_truthful, ContractBundle, Session, NO_ARGS},
+    };
+
+    #[test]
+    fn steps_survive_json_roundtrip() {
+        let step = ReplayStep {
+            kind: StepKind::Call {
+                address: vec![1; 32],
+                message: "flip".to_string(),
+            },
+            actor: vec![2; 32],
+            args: vec!["true".to_string()],
+            value: vec![0],
+            block: 7,
+            outcome: Err("Contract call has been reverted".to_string()),
+            events: vec![vec![3, 4]],
+        };
+
+        assert_eq!(step_from_json(&step_to_json(&step)).unwrap(), step);
+    }
+
+    #[test]
+    fn recorded_log_is_replayed() {
+        let dir = save_truthful("replay");
+        let mut session = Session::<MinimalSandbox>::default().with_replay_recording(true);
+        session
+            .deploy_bundle(
+                ContractBundle::load(dir.join("truthful.contract")).unwrap(),
+                "new",
+                NO_ARGS,
+                vec![],
+                None,
+            )
+            .unwrap();
+        session
+            .call::<_, bool>("get", NO_ARGS, None)
+            .unwrap()
+            .unwrap();
+
+        session.replay_log().save(dir.join("replay.json")).unwrap();
+        let mut log = ReplayLog::load(dir.join("replay.json")).unwrap();
+        assert_eq!(&log, session.replay_log());
+        Session::<MinimalSandbox>::default().replay(&log).unwrap();
+
+        log.steps[1].outcome = Ok(vec![0, 0]);
+        assert!(matches!(
+            Session::<MinimalSandbox>::default().replay(&log),
+            Err(ReplayError::Mismatch {
+                step: 1,
+                what: "outcome",
+                ..
+            })
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,426 @@
+//! Declarative scenarios: contract interactions described in a TOML file.
+//!
+//! A scenario is an ordered list of steps. Every step either deploys a contract from a bundle or
+//! calls a contract, optionally checking the outcome:
+//!
+//! ```toml
+//! [[step]]
+//! deploy = "target/ink/token.contract"   # relative to the scenario file
+//! name = "token"
+//! constructor = "new"
+//! args = ["1000"]
+//!
+//! [[step]]
+//! call = "transfer"
+//! contract = "token"                     # the last deployed contract by default
+//! actor = "alice"                        # SS58 address or a name (see `named_account`)
+//! args = ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY", "2000"]
+//! expect_revert = "Err(InsufficientBalance)"
+//!
+//! [[step]]
+//! call = "total_supply"
+//! expect_return = "1000"
+//! expect_events = []
+//! ```
+//!
+//! Returned values and events are compared with their decoded form (as printed by
+//! `contract-transcode`, without the `Ok` layer that wraps every message result), ignoring
+//! whitespace. `expect_revert` can be `true` (any revert) or the expected decoded revert value.
+//! Scenarios are run against `MinimalSandbox` sessions, since only these can decode contract events.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    minimal::MinimalSandbox,
+    session::{
+        builder::named_account, error::SessionError, trace::unwrap_ok, ContractBundle, Session,
+    },
+    AccountId32, Ss58Codec,
+};
+
+/// A sequence of contract interactions with their expected outcomes.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// The steps, in the order of execution.
+    #[serde(rename = "step", default)]
+    pub steps: Vec<ScenarioStep>,
+    /// The directory against which bundle paths are resolved.
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+/// A single deployment or call. Exactly one of `deploy` and `call` must be set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioStep {
+    /// The path of the bundle to deploy.
+    pub deploy: Option<PathBuf>,
+    /// The name under which the deployed contract can be referred to in `contract`.
+    pub name: Option<String>,
+    /// The constructor to deploy with (`new` by default).
+    pub constructor: Option<String>,
+    /// The deployment salt (its UTF-8 bytes are used).
+    pub salt: Option<String>,
+    /// The message to call.
+    pub call: Option<String>,
+    /// The name of the contract to call. The last deployed contract is called by default.
+    pub contract: Option<String>,
+    /// The account performing the step: an SS58 address or a name. The actor remains set for the
+    /// following steps.
+    pub actor: Option<String>,
+    /// The arguments of the constructor or message.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The value transferred with the step.
+    pub value: Option<u128>,
+    /// The expected (decoded) return value of the call.
+    pub expect_return: Option<String>,
+    /// Whether the step is expected to revert, or the expected (decoded) revert value of the call.
+    pub expect_revert: Option<ExpectedRevert>,
+    /// The expected (decoded) contract events emitted during the step.
+    pub expect_events: Option<Vec<String>>,
+}
+
+/// The expected revert of a step.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ExpectedRevert {
+    /// `true` if the step must revert, `false` if it must succeed.
+    Any(bool),
+    /// The step must revert with this (decoded) value.
+    Value(String),
+}
+
+/// Errors that can occur while loading or running a scenario.
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    /// The scenario file is malformed.
+    #[error("Invalid scenario file: {0}")]
+    Format(String),
+    /// The step is inconsistent (e.g. refers to an unknown contract).
+    #[error("Step {step}: {message}")]
+    InvalidStep {
+        /// The index of the failed step.
+        step: usize,
+        /// The description of the problem.
+        message: String,
+    },
+    /// The interaction failed unexpectedly.
+    #[error("Step {step}: {error}")]
+    Session {
+        /// The index of the failed step.
+        step: usize,
+        /// The underlying error.
+        error: SessionError,
+    },
+    /// The interaction behaved differently than expected.
+    #[error("Step {step}: {what} mismatch\n  expected: {expected}\n  actual:   {actual}")]
+    Mismatch {
+        /// The index of the failed step.
+        step: usize,
+        /// What differs (`outcome`, `return value`, `revert value` or `events`).
+        what: &'static str,
+        /// The expected value.
+        expected: String,
+        /// The actual value.
+        actual: String,
+    },
+}
+
+impl Scenario {
+    /// Loads the scenario from the file under `path`. Bundle paths are resolved against the
+    /// directory of the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScenarioError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|err| {
+            ScenarioError::Format(format!("Failed to read {}: {err}", path.display()))
+        })?;
+        Self::from_toml(&content, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parses the scenario from `content`. Bundle paths are resolved against `base_dir`.
+    pub fn from_toml<P: Into<PathBuf>>(content: &str, base_dir: P) -> Result<Self, ScenarioError> {
+        let mut scenario: Self =
+            toml::from_str(content).map_err(|err| ScenarioError::Format(err.to_string()))?;
+        scenario.base_dir = base_dir.into();
+        Ok(scenario)
+    }
+
+    /// Runs the scenario against `session`. Stops at the first failed step. The session actor is
+    /// restored afterwards.
+    pub fn run(&self, session: &mut Session<MinimalSandbox>) -> Result<(), ScenarioError> {
+        let actor = session.get_actor();
+        let mut contracts = BTreeMap::new();
+        let result = self
+            .steps
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, step)| self.run_step(session, &mut contracts, index, step));
+        session.set_actor(actor);
+        result
+    }
+
+    fn run_step(
+        &self,
+        session: &mut Session<MinimalSandbox>,
+        contracts: &mut BTreeMap<String, AccountId32>,
+        index: usize,
+        step: &ScenarioStep,
+    ) -> Result<(), ScenarioError> {
+        let session_error = |error: SessionError| ScenarioError::Session { step: index, error };
+        let invalid = |message: &str| ScenarioError::InvalidStep {
+            step: index,
+            message: message.to_string(),
+        };
+
+        if let Some(actor) = &step.actor {
+            session.set_actor(
+                AccountId32::from_ss58check(actor).unwrap_or_else(|_| named_account(actor)),
+            );
+        }
+
+        let (outcome, transcoder, message) = match (&step.deploy, &step.call) {
+            (Some(path), None) => {
+                if step.contract.is_some()
+                    || step.expect_return.is_some()
+                    || matches!(step.expect_revert, Some(ExpectedRevert::Value(_)))
+                {
+                    return Err(invalid(
+                        "`contract`, `expect_return` and revert values apply only to calls",
+                    ));
+                }
+                let bundle = ContractBundle::load(self.base_dir.join(path))
+                    .map_err(|err| session_error(err.into()))?;
+                let transcoder = bundle.transcoder.clone();
+                let outcome = session.deploy_bundle(
+                    bundle,
+                    step.constructor.as_deref().unwrap_or("new"),
+                    &step.args,
+                    step.salt.clone().unwrap_or_default().into_bytes(),
+                    step.value,
+                );
+                if let (Ok(address), Some(name)) = (&outcome, &step.name) {
+                    contracts.insert(name.clone(), address.clone());
+                }
+                (outcome.map(|_| ()), transcoder, None)
+            }
+            (None, Some(message)) => {
+                if step.name.is_some() || step.constructor.is_some() || step.salt.is_some() {
+                    return Err(invalid(
+                        "`name`, `constructor` and `salt` apply only to deployments",
+                    ));
+                }
+                let address = match &step.contract {
+                    Some(name) => contracts
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| invalid(&format!("unknown contract `{name}`")))?,
+                    None => session
+                        .record()
+                        .deploy_returns()
+                        .last()
+                        .cloned()
+                        .ok_or_else(|| session_error(SessionError::NoContract))?,
+                };
+                let transcoder = session
+                    .transcoders
+                    .get(&address)
+                    .ok_or_else(|| session_error(SessionError::NoTranscoder))?;
+                let outcome =
+                    session.call_with_address::<_, ()>(address, message, &step.args, step.value);
+                (outcome.map(|_| ()), transcoder, Some(message))
+            }
+            _ => return Err(invalid("exactly one of `deploy` and `call` must be set")),
+        };
+
+        let decode = |data: &[u8]| {
+            let message = message.expect("Only calls return values");
+            transcoder
+                .decode_message_return(message, &mut &*data)
+                .map(|value| unwrap_ok(&value).to_string())
+                .map_err(|err| session_error(SessionError::Decoding(err.to_string())))
+        };
+        let mismatch = |what: &'static str, expected: &str, actual: &str| ScenarioError::Mismatch {
+            step: index,
+            what,
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        };
+
+        let expects_revert = !matches!(step.expect_revert, None | Some(ExpectedRevert::Any(false)));
+        match outcome {
+            Ok(()) if expects_revert => return Err(mismatch("outcome", "revert", "success")),
+            Ok(()) => {
+                if let Some(expected) = &step.expect_return {
+                    let actual = decode(session.record().last_call_return())?;
+                    if !same(expected, &actual) {
+                        return Err(mismatch("return value", expected, &actual));
+                    }
+                }
+            }
+            Err(SessionError::CallReverted(data)) if expects_revert => {
+                if let Some(ExpectedRevert::Value(expected)) = &step.expect_revert {
+                    let actual = decode(&data)?;
+                    if !same(expected, &actual) {
+                        return Err(mismatch("revert value", expected, &actual));
+                    }
+                }
+            }
+            Err(SessionError::DeploymentReverted) if expects_revert => {}
+            Err(error) => return Err(session_error(error)),
+        }
+
+        if let Some(expected) = &step.expect_events {
+            let actual = session
+                .record()
+                .last_event_batch()
+                .contract_events_decoded(&transcoder)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            let matching = expected.len() == actual.len()
+                && expected.iter().zip(&actual).all(|(e, a)| same(e, a));
+            if !matching {
+                return Err(mismatch(
+                    "events",
+                    &format!("{expected:?}"),
+                    &format!("{actual:?}"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares decoded values ignoring whitespace.
+fn same(expected: &str, actual: &str) -> bool {
+    let significant = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    significant(expected) == significant(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::bundle::tests::save_truthful;
+
+    #[test]
+    fn steps_are_parsed() {
+        let scenario = Scenario::from_toml(
+            r#"
+            [[step]]
+            deploy = "flipper.contract"
+            args = ["true"]
+
+            [[step]]
+            call = "get"
+            expect_return = "true"
+            expect_revert = false
+            "#,
+            "contracts",
+        )
+        .unwrap();
+
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(
+            scenario.steps[0].deploy.as_deref(),
+            Some(Path::new("flipper.contract"))
+        );
+        assert_eq!(scenario.steps[1].call.as_deref(), Some("get"));
+        assert_eq!(
+            scenario.steps[1].expect_revert,
+            Some(ExpectedRevert::Any(false))
+        );
+        assert_eq!(scenario.base_dir, PathBuf::from("contracts"));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(matches!(
+            Scenario::from_toml("[[step]]\ncal = \"get\"", ""),
+            Err(ScenarioError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn scenario_runs_against_bundle() {
+        let dir = save_truthful("scenario-runs");
+        let scenario = Scenario::from_toml(
+            r#"
+            [[step]]
+            deploy = "truthful.contract"
+            name = "truthful"
+
+            [[step]]
+            call = "get"
+            contract = "truthful"
+            actor = "alice"
+            expect_return = "true"
+            expect_revert = false
+            expect_events = []
+            "#,
+            &dir,
+        )
+        .unwrap();
+
+        let mut session = Session::<MinimalSandbox>::default();
+        let actor = session.get_actor();
+        scenario.run(&mut session).unwrap();
+        assert_eq!(session.get_actor(), actor);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_expectation_is_reported() {
+        let dir = save_truthful("scenario-fails");
+        let scenario = Scenario::from_toml(
+            r#"
+            [[step]]
+            deploy = "truthful.contract"
+
+            [[step]]
+            call = "get"
+            expect_return = "false"
+            "#,
+            &dir,
+        )
+        .unwrap();
+
+        let result = scenario.run(&mut Session::<MinimalSandbox>::default());
+        assert!(matches!(
+            result,
+            Err(ScenarioError::Mismatch { step: 1, what: "return value", expected, actual })
+                if expected == "false" && actual == "true"
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn calling_before_deploying_fails() {
+        let scenario = Scenario::from_toml("[[step]]\ncall = \"get\"", "").unwrap();
+        assert!(matches!(
+            scenario.run(&mut Session::<MinimalSandbox>::default()),
+            Err(ScenarioError::Session {
+                step: 0,
+                error: SessionError::NoContract
+            })
+        ));
+
+        let scenario =
+            Scenario::from_toml("[[step]]\ncall = \"get\"\ncontract = \"missing\"", "").unwrap();
+        assert!(matches!(
+            scenario.run(&mut Session::<MinimalSandbox>::default()),
+            Err(ScenarioError::InvalidStep { step: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+//! Persisting the state of a session in a file.
+
+use std::{fs, path::Path};
+
+use parity_scale_codec::{Decode, Encode};
+
+use crate::{session::error::SessionError, snapshot::StateSnapshot};
+
+/// Version of the state file format.
+const STATE_FILE_VERSION: u8 = 1;
+
+/// Everything that is needed to resume a session.
+#[derive(Encode, Decode)]
+pub(crate) struct SessionState {
+    version: u8,
+    /// The full sandbox storage.
+    pub snapshot: StateSnapshot,
+    /// Known contracts: the deployed ones in the order of deployment, followed by the ones that
+    /// only have a transcoder registered.
+    pub contracts: Vec<SavedContract>,
+}
+
+/// A contract known to the session.
+#[derive(Encode, Decode)]
+pub(crate) struct SavedContract {
+    /// The encoded address.
+    pub address: Vec<u8>,
+    /// Whether the contract was deployed by the session (and so can be the default target of
+    /// `Session::call`).
+    pub deployed: bool,
+    /// The path of the bundle that the contract was deployed from (if known).
+    pub source: Option<String>,
+    /// The JSON metadata of the registered transcoder (if any).
+    pub metadata: Option<String>,
+}
+
+impl SessionState {
+    pub fn new(snapshot: StateSnapshot, contracts: Vec<SavedContract>) -> Self {
+        Self {
+            version: STATE_FILE_VERSION,
+            snapshot,
+            contracts,
+        }
+    }
+
+    /// Saves the state in the file under `path`.
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        fs::write(path, self.encode()).map_err(|err| {
+            SessionError::StateFile(format!("Failed to write {}: {err}", path.display()))
+        })
+    }
+
+    /// Loads the state from the file under `path`.
+    pub fn load(path: &Path) -> Result<Self, SessionError> {
+        let encoded = fs::read(path).map_err(|err| {
+            SessionError::StateFile(format!("Failed to read {}: {err}", path.display()))
+        })?;
+        let state = Self::decode(&mut encoded.as_slice()).map_err(|err| {
+            SessionError::StateFile(format!("Failed to decode {}: {err}", path.display()))
+        })?;
+
+        match state.version {
+            STATE_FILE_VERSION => Ok(state),
+            version => Err(SessionError::StateFile(format!(
+                "Unsupported state file version: {version}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ink_sandbox::{api::prelude::*, Sandbox};
+
+    use crate::{
+        minimal::MinimalSandbox,
+        session::{
+            bundle::tests::{save_truthful, truthful},
+            ContractBundle, Session, NO_ARGS,
+        },
+    };
+
+    #[test]
+    fn contracts_remain_callable_after_loading() {
+        let dir = save_truthful("state-roundtrip");
+        let mut session = Session::<MinimalSandbox>::default();
+        let deployed = session
+            .deploy_bundle(
+                ContractBundle::load(dir.join("truthful.contract")).unwrap(),
+                "new",
+                NO_ARGS,
+                vec![],
+                None,
+            )
+            .unwrap();
+
+        // Instantiated outside of the session, so only its transcoder is known.
+        let bundle = truthful();
+        let instantiated = session
+            .sandbox()
+            .deploy_contract(
+                bundle.wasm,
+                0,
+                vec![],
+                vec![1],
+                MinimalSandbox::default_actor(),
+                MinimalSandbox::default_gas_limit(),
+                None,
+            )
+            .result
+            .unwrap()
+            .account_id;
+        session.set_transcoder(instantiated.clone(), &bundle.transcoder);
+        session.save_state(dir.join("state")).unwrap();
+
+        // The transcoders must come from the state file, not from the bundle.
+        std::fs::remove_file(dir.join("truthful.contract")).unwrap();
+        let mut loaded = Session::<MinimalSandbox>::default();
+        loaded.load_state(dir.join("state")).unwrap();
+
+        assert_eq!(loaded.record().deploy_returns(), [deployed]);
+        assert_eq!(
+            loaded.call::<_, bool>("get", NO_ARGS, None).unwrap(),
+            Ok(true)
+        );
+        assert_eq!(
+            loaded
+                .call_with_address::<_, bool>(instantiated, "get", NO_ARGS, None)
+                .unwrap(),
+            Ok(true)
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
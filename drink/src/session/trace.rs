@@ -0,0 +1,446 @@
+//! Tracing of contract calls (and chain extension calls) performed during a session.
+
+use std::{
+    fmt::{Debug, Display, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use contract_transcode::Value;
+use parity_scale_codec::Decode;
+
+use crate::{
+    hex, pallet_contracts_debugging::TracingExtT, session::transcoding::TranscoderRegistry,
+};
+
+/// A single contract frame (either a message call or an instantiation) observed during a contract
+/// interaction, together with all the frames it spawned.
+///
+/// If there was a transcoder registered in the session for the contract, the frame's input and
+/// output are also decoded. The `Display` implementation renders the whole frame tree like:
+/// ```text
+/// outer_call(5F.., 5G.., 7) -> 22
+///   middle_call(5G.., 7) -> 22
+///     inner_call(7) -> 22
+/// ```
+#[derive(Clone, Debug)]
+pub struct CallTrace<AccountId> {
+    /// The address of the called (or instantiated) contract.
+    pub contract_address: AccountId,
+    /// `true` for a message call, `false` for an instantiation.
+    pub is_call: bool,
+    /// The raw input data (including the selector).
+    pub input_data: Vec<u8>,
+    /// The raw output data. `None` if the frame failed without returning anything (e.g. trapped).
+    pub output: Option<Vec<u8>>,
+    /// The decoded input data, i.e. the message (or constructor) name together with its arguments.
+    pub decoded_input: Option<Value>,
+    /// The decoded return value of the message.
+    pub decoded_output: Option<Value>,
+    /// The frames spawned by this frame, in the order of execution.
+    pub nested: Vec<CallTrace<AccountId>>,
+    /// The debug messages printed by this frame.
+    ///
+    /// The contracts pallet collects debug messages of all the frames into a single buffer, which
+    /// is not observable by the tracing hooks. Hence, the messages are attributed only when it is
+    /// unambiguous, i.e. when the interaction consists of a single frame. Otherwise, they are
+    /// available only for the whole interaction (see `Record::debug_messages`).
+    pub debug_messages: Vec<String>,
+}
+
+impl<AccountId> CallTrace<AccountId> {
+    fn new(contract_address: AccountId, is_call: bool, input_data: Vec<u8>) -> Self {
+        Self {
+            contract_address,
+            is_call,
+            input_data,
+            output: None,
+            decoded_input: None,
+            decoded_output: None,
+            nested: vec![],
+            debug_messages: vec![],
+        }
+    }
+
+    /// Returns the name of the called message (or constructor), if the input could be decoded.
+    pub fn name(&self) -> Option<String> {
+        match &self.decoded_input {
+            Some(Value::Map(map)) => map.ident(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the frame failed without returning anything.
+    pub fn failed(&self) -> bool {
+        self.output.is_none()
+    }
+
+    /// Attaches `messages` (printed during the whole interaction) to the frame that printed them.
+    /// Returns `false` if they cannot be attributed, i.e. if there are nested frames.
+    pub(crate) fn attribute_debug_messages(&mut self, messages: &[String]) -> bool {
+        if !self.nested.is_empty() {
+            return false;
+        }
+        self.debug_messages = messages.to_vec();
+        true
+    }
+
+    /// Iterates over this frame and all of its (transitively) nested frames, in the order of
+    /// execution start.
+    pub fn frames(&self) -> Vec<&CallTrace<AccountId>> {
+        let mut frames = vec![self];
+        for nested in &self.nested {
+            frames.extend(nested.frames());
+        }
+        frames
+    }
+}
+
+impl<AccountId: Ord> CallTrace<AccountId> {
+    /// Decodes input and output of this frame and all the nested frames, using transcoders
+    /// registered for the corresponding contracts.
+    pub(crate) fn decode(&mut self, transcoders: &TranscoderRegistry<AccountId>) {
+        if let Some(transcoder) = transcoders.get(&self.contract_address) {
+            let decoded_input = match self.is_call {
+                true => transcoder.decode_contract_message(&mut self.input_data.as_slice()),
+                false => transcoder.decode_contract_constructor(&mut self.input_data.as_slice()),
+            };
+            self.decoded_input = decoded_input.ok();
+
+            if let (true, Some(name), Some(output)) = (self.is_call, self.name(), &self.output) {
+                self.decoded_output = transcoder
+                    .decode_message_return(&name, &mut output.as_slice())
+                    .ok();
+            }
+        }
+
+        for nested in &mut self.nested {
+            nested.decode(transcoders);
+        }
+    }
+}
+
+impl<AccountId: Debug> CallTrace<AccountId> {
+    fn fmt_indented(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}", "", indent = 2 * depth)?;
+
+        match &self.decoded_input {
+            Some(Value::Map(map)) => {
+                let args = map.values().map(ToString::to_string).collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{}({})",
+                    map.ident().unwrap_or_default(),
+                    args.join(", ")
+                )?;
+            }
+            _ => write!(
+                f,
+                "<{} {:?} with {}>",
+                if self.is_call { "call" } else { "instantiate" },
+                self.contract_address,
+                hex::encode(&self.input_data)
+            )?,
+        }
+
+        match (&self.output, &self.decoded_output) {
+            (None, _) => write!(f, " -> <failed>")?,
+            (Some(_), Some(value)) => write!(f, " -> {}", unwrap_ok(value))?,
+            (Some(output), None) if self.is_call => write!(f, " -> {}", hex::encode(output))?,
+            (Some(_), None) => {}
+        }
+
+        for message in &self.debug_messages {
+            writeln!(f)?;
+            write!(f, "{:indent$}| {message}", "", indent = 2 * depth + 2)?;
+        }
+
+        for nested in &self.nested {
+            writeln!(f)?;
+            nested.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl<AccountId: Debug> Display for CallTrace<AccountId> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Every ink! message wraps its return value in `Result<_, LangError>`. For readability, we skip
+/// the `Ok` layer.
+pub(super) fn unwrap_ok(value: &Value) -> &Value {
+    match value {
+        Value::Tuple(tuple) if tuple.ident().as_deref() == Some("Ok") => {
+            let mut values = tuple.values();
+            match (values.next(), values.next()) {
+                (Some(inner), None) => inner,
+                _ => value,
+            }
+        }
+        _ => value,
+    }
+}
+
+/// A single chain extension function call made by a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainExtensionCall<AccountId> {
+    /// The address of the contract that called the chain extension.
+    pub contract_address: AccountId,
+    /// The id of the chain extension.
+    pub ext_id: u16,
+    /// The id of the called function.
+    pub func_id: u16,
+    /// The raw input passed by the contract.
+    pub input: Vec<u8>,
+    /// The raw output passed back to the contract.
+    pub output: Vec<u8>,
+    /// The return code passed back to the contract.
+    pub ret_code: u32,
+}
+
+/// Frames collected by `CallTraceCollector`.
+struct CollectedFrames<AccountId> {
+    /// Whether contract frames should be collected at all. Chain extension calls are collected
+    /// regardless of this flag.
+    enabled: bool,
+    /// Frames that have started, but not finished yet. The last one is the currently executed.
+    stack: Vec<CallTrace<AccountId>>,
+    /// Finished top-level frames.
+    finished: Vec<CallTrace<AccountId>>,
+    /// Chain extension calls, in the order of execution.
+    chain_extension_calls: Vec<ChainExtensionCall<AccountId>>,
+}
+
+/// Tracing extension that builds `CallTrace`s (and `ChainExtensionCall`s) out of the low-level
+/// tracing callbacks.
+///
+/// The collector is cheaply cloneable - all the clones share the collected frames. This way, the
+/// session can keep one copy, while the other one is registered in the sandbox.
+pub(crate) struct CallTraceCollector<AccountId> {
+    frames: Arc<Mutex<CollectedFrames<AccountId>>>,
+}
+
+impl<AccountId> Clone for CallTraceCollector<AccountId> {
+    fn clone(&self) -> Self {
+        Self {
+            frames: Arc::clone(&self.frames),
+        }
+    }
+}
+
+impl<AccountId> CallTraceCollector<AccountId> {
+    pub fn new() -> Self {
+        Self {
+            frames: Arc::new(Mutex::new(CollectedFrames {
+                enabled: false,
+                stack: vec![],
+                finished: vec![],
+                chain_extension_calls: vec![],
+            })),
+        }
+    }
+
+    /// Enables or disables collecting contract frames. When disabled, the tracing callbacks do not
+    /// build any frames.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut frames = self
+            .frames
+            .lock()
+            .expect("Should be able to acquire frames");
+        frames.enabled = enabled;
+        frames.stack.clear();
+    }
+
+    /// Returns all the finished top-level frames and resets the collector.
+    pub fn take(&self) -> Vec<CallTrace<AccountId>> {
+        let mut frames = self
+            .frames
+            .lock()
+            .expect("Should be able to acquire frames");
+        frames.stack.clear();
+        std::mem::take(&mut frames.finished)
+    }
+
+    /// Returns all the collected chain extension calls and forgets them.
+    pub fn take_chain_extension_calls(&self) -> Vec<ChainExtensionCall<AccountId>> {
+        std::mem::take(
+            &mut self
+                .frames
+                .lock()
+                .expect("Should be able to acquire frames")
+                .chain_extension_calls,
+        )
+    }
+
+    fn finish_frame(&self, output: Option<Vec<u8>>) {
+        let mut frames = self
+            .frames
+            .lock()
+            .expect("Should be able to acquire frames");
+        if !frames.enabled {
+            return;
+        }
+        let Some(mut frame) = frames.stack.pop() else {
+            return;
+        };
+        frame.output = output;
+
+        match frames.stack.last_mut() {
+            Some(parent) => parent.nested.push(frame),
+            None => frames.finished.push(frame),
+        }
+    }
+}
+
+impl<AccountId: Decode> TracingExtT for CallTraceCollector<AccountId> {
+    fn before_call(&self, contract_address: Vec<u8>, is_call: bool, input_data: Vec<u8>) {
+        let mut frames = self
+            .frames
+            .lock()
+            .expect("Should be able to acquire frames");
+        if !frames.enabled {
+            return;
+        }
+        let contract_address = Decode::decode(&mut &contract_address[..])
+            .expect("Contract address should be decodable");
+        frames
+            .stack
+            .push(CallTrace::new(contract_address, is_call, input_data));
+    }
+
+    fn after_call(
+        &self,
+        _contract_address: Vec<u8>,
+        _is_call: bool,
+        _input_data: Vec<u8>,
+        result: Vec<u8>,
+    ) {
+        self.finish_frame(Some(result));
+    }
+
+    fn after_failed_call(&self, _contract_address: Vec<u8>, _is_call: bool, _input_data: Vec<u8>) {
+        self.finish_frame(None);
+    }
+
+    fn after_chain_extension_call(
+        &self,
+        contract_address: Vec<u8>,
+        ext_id: u16,
+        func_id: u16,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        ret_code: u32,
+    ) {
+        let contract_address = Decode::decode(&mut &contract_address[..])
+            .expect("Contract address should be decodable");
+        self.frames
+            .lock()
+            .expect("Should be able to acquire frames")
+            .chain_extension_calls
+            .push(ChainExtensionCall {
+                contract_address,
+                ext_id,
+                func_id,
+                input,
+                output,
+                ret_code,
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parity_scale_codec::Encode;
+
+    use super::*;
+
+    fn collector() -> CallTraceCollector<u32> {
+        let collector = CallTraceCollector::new();
+        collector.set_enabled(true);
+        collector
+    }
+
+    #[test]
+    fn nested_frames_are_attached_to_their_parents() {
+        let collector = collector();
+
+        collector.before_call(1u32.encode(), true, vec![1]);
+        collector.before_call(2u32.encode(), true, vec![2]);
+        collector.before_call(3u32.encode(), false, vec![3]);
+        collector.after_call(3u32.encode(), false, vec![3], vec![30]);
+        collector.after_call(2u32.encode(), true, vec![2], vec![20]);
+        collector.before_call(4u32.encode(), true, vec![4]);
+        collector.after_call(4u32.encode(), true, vec![4], vec![40]);
+        collector.after_call(1u32.encode(), true, vec![1], vec![10]);
+
+        let traces = collector.take();
+        assert_eq!(traces.len(), 1);
+        let root = &traces[0];
+        assert_eq!(root.contract_address, 1);
+        assert_eq!(root.output, Some(vec![10]));
+        assert_eq!(
+            root.nested
+                .iter()
+                .map(|frame| frame.contract_address)
+                .collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert_eq!(root.nested[0].nested[0].contract_address, 3);
+        assert!(!root.nested[0].nested[0].is_call);
+        assert_eq!(
+            root.frames()
+                .iter()
+                .map(|frame| frame.contract_address)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+        assert!(collector.take().is_empty());
+    }
+
+    #[test]
+    fn failed_frames_have_no_output() {
+        let collector = collector();
+
+        collector.before_call(1u32.encode(), true, vec![1]);
+        collector.before_call(2u32.encode(), true, vec![2]);
+        collector.after_failed_call(2u32.encode(), true, vec![2]);
+        collector.after_call(1u32.encode(), true, vec![1], vec![10]);
+
+        let traces = collector.take();
+        let root = &traces[0];
+        assert!(!root.failed());
+        assert!(root.nested[0].failed());
+        assert_eq!(root.nested[0].output, None);
+    }
+
+    #[test]
+    fn debug_messages_are_attributed_only_to_single_frames() {
+        let messages = vec!["hello".to_string()];
+
+        let mut single = CallTrace::new(1u32, true, vec![1]);
+        assert!(single.attribute_debug_messages(&messages));
+        assert_eq!(single.debug_messages, messages);
+
+        let mut nested = CallTrace::new(1u32, true, vec![1]);
+        nested.nested.push(CallTrace::new(2u32, true, vec![2]));
+        assert!(!nested.attribute_debug_messages(&messages));
+        assert!(nested
+            .frames()
+            .iter()
+            .all(|frame| frame.debug_messages.is_empty()));
+    }
+
+    #[test]
+    fn frames_are_not_collected_when_disabled() {
+        let collector = CallTraceCollector::<u32>::new();
+
+        collector.before_call(1u32.encode(), true, vec![1]);
+        collector.after_chain_extension_call(1u32.encode(), 0, 1, vec![], vec![], 0);
+        collector.after_call(1u32.encode(), true, vec![1], vec![10]);
+
+        assert!(collector.take().is_empty());
+        assert_eq!(collector.take_chain_extension_calls().len(), 1);
+    }
+}
@@ -1,15 +1,18 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
 
 use contract_transcode::ContractMessageTranscoder;
 
 pub struct TranscoderRegistry<Contract: Ord> {
     transcoders: BTreeMap<Contract, Arc<ContractMessageTranscoder>>,
+    /// Paths of the bundles that the transcoders come from (if known).
+    sources: BTreeMap<Contract, PathBuf>,
 }
 
 impl<Contract: Ord> TranscoderRegistry<Contract> {
     pub fn new() -> Self {
         Self {
             transcoders: BTreeMap::new(),
+            sources: BTreeMap::new(),
         }
     }
 
@@ -20,4 +23,17 @@ impl<Contract: Ord> TranscoderRegistry<Contract> {
     pub fn get(&self, contract: &Contract) -> Option<Arc<ContractMessageTranscoder>> {
         self.transcoders.get(contract).map(Arc::clone)
     }
+
+    /// Returns the contracts with a registered transcoder.
+    pub fn contracts(&self) -> impl Iterator<Item = &Contract> {
+        self.transcoders.keys()
+    }
+
+    pub fn register_source(&mut self, contract: Contract, path: PathBuf) {
+        self.sources.insert(contract, path);
+    }
+
+    pub fn source(&self, contract: &Contract) -> Option<&PathBuf> {
+        self.sources.get(contract)
+    }
 }
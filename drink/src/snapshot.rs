@@ -0,0 +1,41 @@
+//! Capturing and restoring the full storage of a sandbox, and importing storage of other chains.
+
+#[cfg(feature = "session")]
+mod dump;
+
+#[cfg(feature = "session")]
+pub use dump::StorageDump;
+use parity_scale_codec::{Decode, Encode};
+
+use crate::{frame_support::sp_runtime::testing::H256, ink_sandbox::Snapshot};
+
+/// Raw trie nodes of the storage (including child tries), together with their reference counts.
+pub type RawStorage = Vec<(Vec<u8>, (Vec<u8>, i32))>;
+
+/// The full storage of a sandbox at some point. Unlike `ink_sandbox::Snapshot`, it can be
+/// SCALE-encoded (e.g. to be saved in a file).
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct StateSnapshot {
+    /// The trie nodes.
+    pub raw_storage: RawStorage,
+    /// The root of the state trie.
+    pub storage_root: H256,
+}
+
+impl From<Snapshot> for StateSnapshot {
+    fn from(snapshot: Snapshot) -> Self {
+        Self {
+            raw_storage: snapshot.storage,
+            storage_root: snapshot.storage_root,
+        }
+    }
+}
+
+impl From<StateSnapshot> for Snapshot {
+    fn from(snapshot: StateSnapshot) -> Self {
+        Self {
+            storage: snapshot.raw_storage,
+            storage_root: snapshot.storage_root,
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Importing storage entries of other chains.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use parity_scale_codec::{Compact, Decode};
+
+use crate::{
+    frame_support::sp_runtime::{testing::H256, StateVersion},
+    hex,
+    ink_sandbox::TestExternalities,
+    snapshot::RawStorage,
+    DrinkResult, Error, Sandbox,
+};
+
+/// The prefix of the top-level keys under which the roots of the default child tries are kept.
+const CHILD_STORAGE_PREFIX: &[u8] = b":child_storage:default:";
+
+/// Storage entries (e.g. of a real chain), which can be imported into a sandbox.
+///
+/// Importing the entries doesn't replace the whole sandbox storage - the sandbox keeps its own
+/// runtime and only the imported keys are overwritten. Usually, it is enough to import the storage
+/// of the contracts pallet (see `StorageDump::retain_prefixes`) to reproduce the state of the
+/// contracts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageDump {
+    /// Top-level entries.
+    pub top: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Entries of the default child tries (like contract storage), keyed by the child trie key
+    /// (without the `:child_storage:default:` prefix).
+    pub children_default: BTreeMap<Vec<u8>, BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+/// The only supported version of the `frame-remote-externalities` snapshot format.
+const TRY_RUNTIME_SNAPSHOT_VERSION: u16 = 3;
+
+/// The format of the snapshots created by `frame-remote-externalities` (e.g. with
+/// `try-runtime create-snapshot`), following the version number.
+#[derive(Decode)]
+struct RemoteExternalitiesSnapshot {
+    state_version: StateVersion,
+    _block_hash: H256,
+    raw_storage: RawStorage,
+    storage_root: H256,
+}
+
+impl StorageDump {
+    /// Loads the dump from the file under `path`. The file can be either a JSON key/value dump (see
+    /// `StorageDump::from_json`) or a `try-runtime` snapshot (see
+    /// `StorageDump::from_try_runtime_snapshot`).
+    pub fn load<P: AsRef<Path>>(path: P) -> DrinkResult<Self> {
+        let content = fs::read(&path).map_err(|err| {
+            Error::SnapshotLoadFailed(format!("Failed to read {}: {err}", path.as_ref().display()))
+        })?;
+
+        match content.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => Self::from_json(&String::from_utf8_lossy(&content)),
+            _ => Self::from_try_runtime_snapshot(&content),
+        }
+    }
+
+    /// Parses a JSON key/value dump with hex-encoded keys and values, in the format of the raw chain
+    /// specification: `{"top": {"0x..": "0x.."}, "childrenDefault": {"0x..": {"0x..": "0x.."}}}`.
+    /// A whole raw chain specification (with the dump under `genesis.raw`) is accepted as well.
+    pub fn from_json(json: &str) -> DrinkResult<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|err| Error::SnapshotLoadFailed(format!("Invalid JSON: {err}")))?;
+        let raw = value.pointer("/genesis/raw").unwrap_or(&value);
+
+        let mut dump = Self {
+            top: parse_entries(raw.get("top").unwrap_or(&serde_json::Value::Null))?,
+            ..Default::default()
+        };
+        if let Some(children) = raw.get("childrenDefault").and_then(|c| c.as_object()) {
+            for (child_key, entries) in children {
+                dump.children_default
+                    .insert(parse_hex(child_key)?, parse_entries(entries)?);
+            }
+        }
+        Ok(dump)
+    }
+
+    /// Decodes a snapshot created by `frame-remote-externalities` (e.g. with
+    /// `try-runtime create-snapshot`). Only the version 3 of the snapshot format is supported.
+    pub fn from_try_runtime_snapshot(mut encoded: &[u8]) -> DrinkResult<Self> {
+        let invalid = |err: parity_scale_codec::Error| {
+            Error::SnapshotLoadFailed(format!("Invalid try-runtime snapshot: {err}"))
+        };
+
+        let Compact(version) = Compact::<u16>::decode(&mut encoded).map_err(invalid)?;
+        if version != TRY_RUNTIME_SNAPSHOT_VERSION {
+            return Err(Error::SnapshotLoadFailed(format!(
+                "Unsupported try-runtime snapshot version: {version} (only version \
+                 {TRY_RUNTIME_SNAPSHOT_VERSION} is supported)"
+            )));
+        }
+        let snapshot = RemoteExternalitiesSnapshot::decode(&mut encoded).map_err(invalid)?;
+
+        let mut ext = TestExternalities::from_raw_snapshot(
+            snapshot.raw_storage,
+            snapshot.storage_root,
+            snapshot.state_version,
+        );
+        Ok(ext.execute_with(Self::read_current))
+    }
+
+    /// Reads all the entries of the current externalities.
+    fn read_current() -> Self {
+        let mut dump = Self::default();
+        let mut key = vec![];
+        while let Some(next) = sp_io::storage::next_key(&key) {
+            match next.strip_prefix(CHILD_STORAGE_PREFIX) {
+                Some(child_key) => {
+                    let mut entries = BTreeMap::new();
+                    let mut child_entry_key = vec![];
+                    while let Some(next_child) =
+                        sp_io::default_child_storage::next_key(child_key, &child_entry_key)
+                    {
+                        if let Some(value) =
+                            sp_io::default_child_storage::get(child_key, &next_child)
+                        {
+                            entries.insert(next_child.clone(), value.to_vec());
+                        }
+                        child_entry_key = next_child;
+                    }
+                    dump.children_default.insert(child_key.to_vec(), entries);
+                }
+                None => {
+                    if let Some(value) = sp_io::storage::get(&next) {
+                        dump.top.insert(next.clone(), value.to_vec());
+                    }
+                }
+            }
+            key = next;
+        }
+        dump
+    }
+
+    /// Keeps only the top-level entries whose keys start with one of `prefixes` (e.g.
+    /// `twox_128(b"Contracts")` for the contracts pallet). Child tries are kept intact.
+    pub fn retain_prefixes(mut self, prefixes: &[&[u8]]) -> Self {
+        self.top
+            .retain(|key, _| prefixes.iter().any(|prefix| key.starts_with(prefix)));
+        self
+    }
+
+    /// Writes all the entries into the storage of `sandbox`.
+    pub fn apply<S: Sandbox>(&self, sandbox: &mut S) {
+        sandbox.execute_with(|| {
+            for (key, value) in &self.top {
+                sp_io::storage::set(key, value);
+            }
+            for (child_key, entries) in &self.children_default {
+                for (key, value) in entries {
+                    sp_io::default_child_storage::set(child_key, key, value);
+                }
+            }
+        });
+    }
+}
+
+fn parse_entries(entries: &serde_json::Value) -> DrinkResult<BTreeMap<Vec<u8>, Vec<u8>>> {
+    let entries = entries.as_object().ok_or_else(|| {
+        Error::SnapshotLoadFailed("Storage entries should be a JSON object".to_string())
+    })?;
+    entries
+        .iter()
+        .map(|(key, value)| {
+            let value = value.as_str().ok_or_else(|| {
+                Error::SnapshotLoadFailed(format!("Value of {key} should be a hex string"))
+            })?;
+            Ok((parse_hex(key)?, parse_hex(value)?))
+        })
+        .collect()
+}
+
+fn parse_hex(hex: &str) -> DrinkResult<Vec<u8>> {
+    hex::decode(hex).ok_or_else(|| Error::SnapshotLoadFailed(format!("Invalid hex: {hex}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use parity_scale_codec::Encode;
+
+    use super::*;
+    use crate::minimal::MinimalSandbox;
+
+    fn entries(entries: &[(&[u8], &[u8])]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect()
+    }
+
+    fn assert_load_failed<T: Debug>(result: DrinkResult<T>, message: &str) {
+        match result {
+            Err(Error::SnapshotLoadFailed(err)) => assert!(err.contains(message), "{err}"),
+            other => panic!("Expected `SnapshotLoadFailed`, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_hex_accepts_prefixed_and_bare_hex() {
+        assert_eq!(parse_hex("0x0aff").unwrap(), vec![0x0a, 0xff]);
+        assert_eq!(parse_hex("0aff").unwrap(), vec![0x0a, 0xff]);
+    }
+
+    #[test]
+    fn parse_hex_rejects_malformed_hex() {
+        assert_load_failed(parse_hex("0x€a"), "Invalid hex: 0x€a");
+        assert_load_failed(parse_hex("0x123"), "Invalid hex");
+    }
+
+    #[test]
+    fn json_dump_is_parsed() {
+        let dump = StorageDump::from_json(
+            r#"{
+                "top": { "0x01": "0x02", "03": "04" },
+                "childrenDefault": { "0x0a": { "0x0b": "0x0c" } }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(dump.top, entries(&[(&[1], &[2]), (&[3], &[4])]));
+        assert_eq!(
+            dump.children_default,
+            BTreeMap::from([(vec![0x0a], entries(&[(&[0x0b], &[0x0c])]))])
+        );
+    }
+
+    #[test]
+    fn raw_chain_spec_is_parsed() {
+        let dump = StorageDump::from_json(
+            r#"{ "name": "x", "genesis": { "raw": { "top": { "0x01": "0x02" } } } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(dump.top, entries(&[(&[1], &[2])]));
+        assert!(dump.children_default.is_empty());
+    }
+
+    #[test]
+    fn malformed_json_dump_is_rejected() {
+        assert_load_failed(StorageDump::from_json("{"), "Invalid JSON");
+        assert_load_failed(
+            StorageDump::from_json(r#"{ "top": { "0x01": 2 } }"#),
+            "Value of 0x01 should be a hex string",
+        );
+        assert_load_failed(
+            StorageDump::from_json(r#"{ "top": { "0x01": "0xzz" } }"#),
+            "Invalid hex: 0xzz",
+        );
+    }
+
+    fn try_runtime_snapshot(version: u16) -> Vec<u8> {
+        let mut sandbox = MinimalSandbox::default();
+        sandbox.execute_with(|| sp_io::storage::set(b"drink", b"value"));
+        let snapshot = sandbox.take_snapshot();
+
+        (
+            Compact(version),
+            StateVersion::V1,
+            H256::zero(),
+            snapshot.storage,
+            snapshot.storage_root,
+        )
+            .encode()
+    }
+
+    #[test]
+    fn try_runtime_snapshot_is_decoded() {
+        let dump = StorageDump::from_try_runtime_snapshot(&try_runtime_snapshot(3)).unwrap();
+
+        assert_eq!(dump.top.get(b"drink".as_slice()), Some(&b"value".to_vec()));
+    }
+
+    #[test]
+    fn unsupported_try_runtime_snapshot_version_is_rejected() {
+        assert_load_failed(
+            StorageDump::from_try_runtime_snapshot(&try_runtime_snapshot(2)),
+            "Unsupported try-runtime snapshot version: 2",
+        );
+        assert_load_failed(
+            StorageDump::from_try_runtime_snapshot(&[]),
+            "Invalid try-runtime snapshot",
+        );
+    }
+}
@@ -35,10 +35,14 @@ mod contract_calling_chain_extension {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use drink::{
         create_sandbox,
-        session::{Session, NO_ARGS, NO_ENDOWMENT, NO_SALT},
+        pallet_contracts_debugging::TracingExtT,
+        session::{error::SessionError, Session, NO_ARGS, NO_ENDOWMENT, NO_SALT},
     };
+    use scale::Encode;
 
     use crate::CHAIN_EXTENSION_RETURN_VALUE;
 
@@ -69,4 +73,107 @@ mod tests {
 
         Ok(())
     }
+
+    /// With the minimal sandbox, we don't even need to implement a chain extension - its functions
+    /// can be defined directly in the test.
+    #[drink::test]
+    fn we_can_define_chain_extension_in_test(
+        mut session: Session,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        session
+            .chain_extension()
+            .on_typed(41, |(): ()| CHAIN_EXTENSION_RETURN_VALUE);
+
+        let result: u32 = session
+            .deploy_bundle_and(
+                BundleProvider::local()?,
+                "new",
+                NO_ARGS,
+                NO_SALT,
+                NO_ENDOWMENT,
+            )?
+            .call("call_ce", NO_ARGS, NO_ENDOWMENT)??;
+
+        assert_eq!(result, CHAIN_EXTENSION_RETURN_VALUE);
+
+        let call = session.record().last_chain_extension_call();
+        assert_eq!(call.func_id, 41);
+        assert_eq!(call.output, CHAIN_EXTENSION_RETURN_VALUE.encode());
+
+        Ok(())
+    }
+
+    /// A handler that cannot decode the input passed by the contract fails the call, instead of
+    /// panicking.
+    #[drink::test]
+    fn undecodable_chain_extension_input_fails_the_call(
+        mut session: Session,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // The contract passes no input, so there is no `u64` to decode.
+        session
+            .chain_extension()
+            .on_typed(41, |value: u64| value as u32);
+
+        session.deploy_bundle(
+            BundleProvider::local()?,
+            "new",
+            NO_ARGS,
+            NO_SALT,
+            NO_ENDOWMENT,
+        )?;
+        let result = session.call::<_, u32>("call_ce", NO_ARGS, NO_ENDOWMENT);
+        assert!(matches!(result, Err(SessionError::CallFailed(_))));
+
+        Ok(())
+    }
+
+    /// Chain extension calls are recorded in the session and reported to the registered tracers.
+    #[drink::test]
+    fn chain_extension_calls_are_recorded(
+        mut session: Session,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        struct CountingTracer(Arc<Mutex<Vec<(u16, u16, u32)>>>);
+        impl TracingExtT for CountingTracer {
+            fn after_chain_extension_call(
+                &self,
+                _contract_address: Vec<u8>,
+                ext_id: u16,
+                func_id: u16,
+                _input: Vec<u8>,
+                _output: Vec<u8>,
+                ret_code: u32,
+            ) {
+                self.0.lock().unwrap().push((ext_id, func_id, ret_code));
+            }
+        }
+        let traced = Arc::new(Mutex::new(vec![]));
+        session.add_tracer(CountingTracer(Arc::clone(&traced)));
+
+        // Return code is ignored by the contract (`handle_status = false`), but it is recorded.
+        session.chain_extension().on_extension(
+            0,
+            41,
+            Box::new(|_| Ok((7, CHAIN_EXTENSION_RETURN_VALUE.encode()))),
+        );
+
+        let address = session.deploy_bundle(
+            BundleProvider::local()?,
+            "new",
+            NO_ARGS,
+            NO_SALT,
+            NO_ENDOWMENT,
+        )?;
+        session.call::<_, u32>("call_ce", NO_ARGS, NO_ENDOWMENT)??;
+        session.call::<_, u32>("call_ce", NO_ARGS, NO_ENDOWMENT)??;
+
+        let calls = session.record().chain_extension_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].contract_address, address);
+        assert_eq!((calls[0].ext_id, calls[0].func_id), (0, 41));
+        assert!(calls[0].input.is_empty());
+        assert_eq!(calls[0].ret_code, 7);
+        assert_eq!(*traced.lock().unwrap(), vec![(0, 41, 7); 2]);
+
+        Ok(())
+    }
 }
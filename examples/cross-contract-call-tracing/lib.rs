@@ -61,74 +61,18 @@ mod contract {
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, error::Error};
+    use std::error::Error;
 
-    use drink::{
-        pallet_contracts_debugging::{TracingExt, TracingExtT},
-        session::{contract_transcode::Value, Session, NO_ARGS, NO_ENDOWMENT},
-        AccountId32,
-    };
-    use ink::storage::traits::Storable;
+    use drink::session::{Session, NO_ARGS, NO_ENDOWMENT};
 
     #[drink::contract_bundle_provider]
     enum BundleProvider {}
 
-    thread_local! {
-        static OUTER_ADDRESS:  RefCell<Option<AccountId32>> = RefCell::new(None);
-        static MIDDLE_ADDRESS:  RefCell<Option<AccountId32>> = RefCell::new(None);
-        static INNER_ADDRESS:  RefCell<Option<AccountId32>> = RefCell::new(None);
-    }
-
-    struct TestDebugger;
-    impl TracingExtT for TestDebugger {
-        fn after_call(
-            &self,
-            contract_address: Vec<u8>,
-            is_call: bool,
-            input_data: Vec<u8>,
-            result: Vec<u8>,
-        ) {
-            let contract_address = AccountId32::decode(&mut contract_address.as_slice())
-                .expect("Failed to decode contract address");
-            let transcoder = BundleProvider::local().unwrap().transcoder;
-
-            let data_decoded = if is_call {
-                transcoder.decode_contract_message(&mut input_data.as_slice())
-            } else {
-                transcoder.decode_contract_constructor(&mut input_data.as_slice())
-            }
-            .unwrap();
-
-            let return_decoded = if is_call {
-                let call_name = if contract_address
-                    == OUTER_ADDRESS.with(|a| a.borrow().clone().unwrap())
-                {
-                    "outer_call"
-                } else if contract_address == MIDDLE_ADDRESS.with(|a| a.borrow().clone().unwrap()) {
-                    "middle_call"
-                } else if contract_address == INNER_ADDRESS.with(|a| a.borrow().clone().unwrap()) {
-                    "inner_call"
-                } else {
-                    panic!("Unexpected contract address")
-                };
-
-                transcoder
-                    .decode_message_return(call_name, &mut result.as_slice())
-                    .unwrap()
-            } else {
-                Value::Unit
-            };
-
-            println!(
-                "Contract at address `{contract_address}` has been called with data: \
-                    \n    {data_decoded}\nand returned:\n    {return_decoded}\n"
-            )
-        }
-    }
-
     #[drink::test]
     fn test(mut session: Session) -> Result<(), Box<dyn Error>> {
-        session.set_tracing_extension(TracingExt(Box::new(TestDebugger {})));
+        // With call tracing enabled, every contract interaction is recorded together with all the
+        // nested calls. Calls to contracts with known transcoders are decoded automatically.
+        session.set_call_tracing(true);
 
         let outer_address = session.deploy_bundle(
             BundleProvider::local()?,
@@ -137,7 +81,6 @@ mod tests {
             vec![1],
             NO_ENDOWMENT,
         )?;
-        OUTER_ADDRESS.with(|a| *a.borrow_mut() = Some(outer_address.clone()));
         let middle_address = session.deploy_bundle(
             BundleProvider::local()?,
             "new",
@@ -145,7 +88,6 @@ mod tests {
             vec![2],
             NO_ENDOWMENT,
         )?;
-        MIDDLE_ADDRESS.with(|a| *a.borrow_mut() = Some(middle_address.clone()));
         let inner_address = session.deploy_bundle(
             BundleProvider::local()?,
             "new",
@@ -153,10 +95,9 @@ mod tests {
             vec![3],
             NO_ENDOWMENT,
         )?;
-        INNER_ADDRESS.with(|a| *a.borrow_mut() = Some(inner_address.clone()));
 
         let value: u32 = session.call_with_address(
-            outer_address,
+            outer_address.clone(),
             "outer_call",
             &[
                 &*middle_address.to_string(),
@@ -168,6 +109,24 @@ mod tests {
 
         assert_eq!(value, 22);
 
+        let trace = session.record().last_call_trace();
+        println!("{trace}");
+
+        // Every frame has been decoded using the transcoder registered during deployment.
+        let frames = trace.frames();
+        let called = frames
+            .iter()
+            .map(|frame| (frame.contract_address.clone(), frame.name().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            called,
+            vec![
+                (outer_address, "outer_call".to_string()),
+                (middle_address, "middle_call".to_string()),
+                (inner_address, "inner_call".to_string()),
+            ]
+        );
+
         Ok(())
     }
 }
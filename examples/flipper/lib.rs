@@ -34,7 +34,11 @@ mod flipper {
 mod tests {
     use std::error::Error;
 
-    use drink::session::{Session, NO_ARGS, NO_ENDOWMENT, NO_SALT};
+    use drink::{
+        minimal::MinimalSandbox,
+        sandbox_api::prelude::*,
+        session::{replay::ReplayLog, Session, NO_ARGS, NO_ENDOWMENT, NO_SALT},
+    };
 
     #[drink::contract_bundle_provider]
     enum BundleProvider {}
@@ -71,4 +75,68 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn genesis_setup() -> Result<(), Box<dyn Error>> {
+        let mut session = Session::<MinimalSandbox>::builder()
+            .with_named_account("alice", 1_000_000)
+            .with_contract("flipper", BundleProvider::local()?, "new", &["false"])
+            .build()?;
+
+        let flipper = session.genesis().contract("flipper");
+        let value: bool = session.call_with_address(flipper, "get", NO_ARGS, NO_ENDOWMENT)??;
+        assert_eq!(value, false);
+
+        let alice = session.genesis().account("alice");
+        assert_eq!(session.sandbox().free_balance(&alice), 1_000_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn saving_and_loading_state() -> Result<(), Box<dyn Error>> {
+        let mut session = Session::<MinimalSandbox>::default();
+        session.deploy_bundle(
+            BundleProvider::local()?,
+            "new",
+            &["true"],
+            NO_SALT,
+            NO_ENDOWMENT,
+        )?;
+        session.call::<_, ()>("flip", NO_ARGS, NO_ENDOWMENT)??;
+
+        let state_file = std::env::temp_dir().join("flipper.state");
+        session.save_state(&state_file)?;
+
+        // A fresh session knows the contract (so it can be called without an address) and its
+        // storage.
+        let mut session = Session::<MinimalSandbox>::default();
+        session.load_state(&state_file)?;
+        let value: bool = session.call("get", NO_ARGS, NO_ENDOWMENT)??;
+        assert_eq!(value, false);
+
+        Ok(())
+    }
+
+    #[test]
+    fn replaying() -> Result<(), Box<dyn Error>> {
+        let mut session = Session::<MinimalSandbox>::default().with_replay_recording(true);
+        session.deploy_bundle(
+            BundleProvider::local()?,
+            "new",
+            &["true"],
+            NO_SALT,
+            NO_ENDOWMENT,
+        )?;
+        session.call::<_, ()>("flip", NO_ARGS, NO_ENDOWMENT)??;
+
+        let replay_file = std::env::temp_dir().join("flipper.replay.json");
+        session.replay_log().save(&replay_file)?;
+
+        let log = ReplayLog::load(&replay_file)?;
+        assert_eq!(log.steps().len(), 2);
+        Session::<MinimalSandbox>::default().replay(&log)?;
+
+        Ok(())
+    }
 }
@@ -37,12 +37,21 @@ mod proxy {
 
 #[cfg(test)]
 mod tests {
-    use std::error::Error;
+    use std::{
+        error::Error,
+        sync::{Arc, Mutex},
+    };
 
     use drink::{
-        mock_message,
-        session::{mocking_api::MockingApi, Session, NO_ARGS, NO_ENDOWMENT, NO_SALT},
-        ContractMock,
+        minimal::MinimalSandboxRuntime,
+        mock_message, pallet_contracts,
+        session::{
+            contract_transcode::{Tuple, Value},
+            error::SessionError,
+            mocking_api::MockingApi,
+            Session, NO_ARGS, NO_ENDOWMENT, NO_SALT,
+        },
+        AccountId32, ContractMock, DispatchError, Expectation, Fallback, MockOutcome,
     };
 
     use crate::CALLEE_SELECTOR;
@@ -54,21 +63,307 @@ mod tests {
     fn call_mocked_message(mut session: Session) -> Result<(), Box<dyn Error>> {
         // Firstly, we create the mocked contract.
         const RETURN_VALUE: (u8, u8) = (4, 1);
-        let mocked_contract =
-            ContractMock::new().with_message(CALLEE_SELECTOR, mock_message(|()| RETURN_VALUE));
+        let mocked_contract = ContractMock::new()
+            .with_message(CALLEE_SELECTOR, mock_message(|()| RETURN_VALUE))
+            // We expect the proxy to call the mock exactly once. This is checked at the end of the
+            // test.
+            .with_expectation(Expectation::called(CALLEE_SELECTOR).times(1));
+        let spy = mocked_contract.spy();
 
         // Secondly, we deploy it, similarly to a standard deployment action.
         let mock_address = session.mocking_api().deploy(mocked_contract);
 
         // Now, we can deploy our proper contract and verify its behavior.
+        let proxy_address =
+            session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
         let result: (u8, u8) = session
-            .deploy_bundle_and(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?
             .call_and("forward_call", &[mock_address.to_string()], NO_ENDOWMENT)?
             .record()
             .last_call_return_decoded()?
             .expect("Call was successful");
         assert_eq!(result, RETURN_VALUE);
 
+        // We can also inspect the calls received by the mock.
+        assert_eq!(spy.calls()[0].caller::<AccountId32>(), proxy_address);
+        session.mocking_api().verify_expectations()?;
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn mock_message_of_existing_contract(mut session: Session) -> Result<(), Box<dyn Error>> {
+        // We deploy a real contract...
+        let bundle = BundleProvider::local()?;
+        let address = session.deploy_bundle(bundle.clone(), "new", NO_ARGS, NO_SALT, None)?;
+
+        // ...and override one of its messages. All the other messages would still be handled by
+        // the real contract code. Since we have the contract metadata, we can refer to the message
+        // by its name.
+        const MOCKED_VALUE: (u8, u8) = (1, 2);
+        session.mocking_api().mock_existing_contract(
+            ContractMock::from_bundle(&bundle)
+                .with_message_named("forward_call", mock_message(|_: AccountId32| MOCKED_VALUE))?,
+            address.clone(),
+        );
+
+        // Now, instead of calling `address` (which would fail, since it doesn't support `callee`),
+        // the mocked `forward_call` returns a fixed value.
+        let result: (u8, u8) = session
+            .call_with_address(
+                address.clone(),
+                "forward_call",
+                &[address.to_string()],
+                NO_ENDOWMENT,
+            )?
+            .expect("Call was successful");
+        assert_eq!(result, MOCKED_VALUE);
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn stateful_mock(mut session: Session) -> Result<(), Box<dyn Error>> {
+        // Mocks can keep their own state, so that their answers change over the scenario.
+        let mut counter = 0;
+        let mocked_contract = ContractMock::new().with_message(
+            CALLEE_SELECTOR,
+            mock_message(move |()| {
+                counter += 1;
+                (counter, 0u8)
+            }),
+        );
+        let mock_address = session.mocking_api().deploy(mocked_contract);
+
+        session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
+        for expected in 1..=2 {
+            let result: (u8, u8) = session
+                .call("forward_call", &[mock_address.to_string()], NO_ENDOWMENT)?
+                .expect("Call was successful");
+            assert_eq!(result, (expected, 0));
+        }
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn fallback_passes_through_to_contract(mut session: Session) -> Result<(), Box<dyn Error>> {
+        const RETURN_VALUE: (u8, u8) = (3, 7);
+        let callee = session.mocking_api().deploy(
+            ContractMock::new().with_message(CALLEE_SELECTOR, mock_message(|()| RETURN_VALUE)),
+        );
+        let proxy =
+            session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
+
+        // `forward_call` is not mocked, so the real contract code handles it.
+        let proxy_mock = ContractMock::new().with_fallback(Fallback::PassThrough);
+        let spy = proxy_mock.spy();
+        session
+            .mocking_api()
+            .mock_existing_contract(proxy_mock, proxy.clone());
+
+        let result: (u8, u8) = session
+            .call_with_address(proxy, "forward_call", &[callee.to_string()], NO_ENDOWMENT)?
+            .expect("Call was successful");
+        assert_eq!(result, RETURN_VALUE);
+        assert_eq!(spy.calls().len(), 1);
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn fallback_reverts(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let proxy =
+            session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
+        session.mocking_api().mock_existing_contract(
+            ContractMock::new().with_fallback(Fallback::revert_with(42u8)),
+            proxy.clone(),
+        );
+
+        let error = session
+            .call_with_address::<_, (u8, u8)>(
+                proxy.clone(),
+                "forward_call",
+                &[proxy.to_string()],
+                NO_ENDOWMENT,
+            )
+            .expect_err("Call should be reverted");
+        assert_eq!(error.decode_revert::<Result<(), u8>>()?, Ok(Err(42)));
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn fallback_traps(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let proxy =
+            session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
+        session.mocking_api().mock_existing_contract(
+            ContractMock::new().with_fallback(Fallback::trap()),
+            proxy.clone(),
+        );
+
+        let error = session
+            .call_with_address::<_, (u8, u8)>(
+                proxy.clone(),
+                "forward_call",
+                &[proxy.to_string()],
+                NO_ENDOWMENT,
+            )
+            .expect_err("Call should trap");
+        let trapped: DispatchError =
+            pallet_contracts::Error::<MinimalSandboxRuntime>::ContractTrapped.into();
+        assert!(matches!(error, SessionError::CallFailed(err) if err == trapped));
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn expectations_by_message_name(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let bundle = BundleProvider::local()?;
+        let proxy = session.deploy_bundle(bundle.clone(), "new", NO_ARGS, NO_SALT, None)?;
+
+        // With the contract metadata, expectations can refer to messages by name and the spy
+        // records decoded arguments.
+        let mock = ContractMock::from_bundle(&bundle)
+            .with_message_named("forward_call", mock_message(|_: AccountId32| (0u8, 0u8)))?
+            .with_expectation(Expectation::called_message("forward_call").times(1));
+        let spy = mock.spy();
+        session
+            .mocking_api()
+            .mock_existing_contract(mock, proxy.clone());
+
+        session.call_with_address::<_, (u8, u8)>(
+            proxy.clone(),
+            "forward_call",
+            &[proxy.to_string()],
+            NO_ENDOWMENT,
+        )??;
+
+        session.mocking_api().verify_expectations()?;
+        let calls = spy.calls_to_message("forward_call");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].decoded_args.as_ref().map(Vec::len), Some(1));
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn replacing_mock_with_unmet_expectation(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let proxy =
+            session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
+        session.mocking_api().mock_existing_contract(
+            ContractMock::new().with_expectation(Expectation::called(CALLEE_SELECTOR)),
+            proxy.clone(),
+        );
+        assert!(session.mocking_api().verify_expectations().is_err());
+
+        // The replaced mock is discarded together with its unmet expectation.
+        session
+            .mocking_api()
+            .mock_existing_contract(ContractMock::new(), proxy);
+        session.mocking_api().verify_expectations()?;
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn mock_message_with_values(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let bundle = BundleProvider::local()?;
+        let proxy = session.deploy_bundle(bundle.clone(), "new", NO_ARGS, NO_SALT, None)?;
+
+        // The mock receives decoded arguments and returns a value that is encoded according to the
+        // message return type from the metadata.
+        let received = Arc::new(Mutex::new(vec![]));
+        let received_by_mock = Arc::clone(&received);
+        session.mocking_api().mock_existing_contract(
+            ContractMock::from_bundle(&bundle).with_message_values(
+                "forward_call",
+                move |args| {
+                    *received_by_mock.lock().unwrap() = args;
+                    Value::Tuple(Tuple::new(None, vec![Value::UInt(5), Value::UInt(6)]))
+                },
+            )?,
+            proxy.clone(),
+        );
+
+        let result: (u8, u8) = session
+            .call_with_address(
+                proxy.clone(),
+                "forward_call",
+                &[proxy.to_string()],
+                NO_ENDOWMENT,
+            )?
+            .expect("Call was successful");
+        assert_eq!(result, (5, 6));
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn mock_outcomes(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let proxy =
+            session.deploy_bundle(BundleProvider::local()?, "new", NO_ARGS, NO_SALT, None)?;
+        let mut call_with_outcome = |outcome: MockOutcome| {
+            session.mocking_api().mock_existing_contract(
+                ContractMock::new().with_fallback(Fallback::Outcome(outcome)),
+                proxy.clone(),
+            );
+            session.call_with_address::<_, (u8, u8)>(
+                proxy.clone(),
+                "forward_call",
+                &[proxy.to_string()],
+                NO_ENDOWMENT,
+            )
+        };
+
+        // A raw payload is passed back to the caller as it is.
+        let error = call_with_outcome(MockOutcome::Revert(vec![1, 2, 3])).expect_err("Reverted");
+        assert!(matches!(error, SessionError::CallReverted(payload) if payload == [1, 2, 3]));
+
+        let out_of_gas: DispatchError =
+            pallet_contracts::Error::<MinimalSandboxRuntime>::OutOfGas.into();
+        let error = call_with_outcome(MockOutcome::OutOfGas).expect_err("Out of gas");
+        assert!(matches!(error, SessionError::CallFailed(err) if err == out_of_gas));
+
+        let custom = DispatchError::BadOrigin;
+        let error = call_with_outcome(MockOutcome::DispatchError(custom)).expect_err("Failed");
+        assert!(matches!(error, SessionError::CallFailed(err) if err == custom));
+
+        let result = call_with_outcome(MockOutcome::return_value((1u8, 2u8)))?;
+        assert_eq!(result, Ok((1, 2)));
+
+        Ok(())
+    }
+
+    #[drink::test]
+    fn mock_all_instances_of_code(mut session: Session) -> Result<(), Box<dyn Error>> {
+        let bundle = BundleProvider::local()?;
+        let code_hash = session.upload_bundle(bundle.clone())?;
+        let existing = session.deploy_bundle(bundle.clone(), "new", NO_ARGS, vec![1], None)?;
+
+        const MOCKED_VALUE: (u8, u8) = (9, 9);
+        let mock = ContractMock::new().with_message(
+            ink::selector_bytes!("forward_call"),
+            mock_message(|_: AccountId32| MOCKED_VALUE),
+        );
+        let spy = mock.spy();
+        session.mocking_api().mock_code_hash(code_hash, mock);
+
+        // Both the existing instance and the one deployed after registering the mock are mocked.
+        let later = session.deploy_bundle(bundle, "new", NO_ARGS, vec![2], None)?;
+        for address in [existing, later] {
+            let result: (u8, u8) = session
+                .call_with_address(
+                    address.clone(),
+                    "forward_call",
+                    &[address.to_string()],
+                    NO_ENDOWMENT,
+                )?
+                .expect("Call was successful");
+            assert_eq!(result, MOCKED_VALUE);
+        }
+        assert_eq!(spy.calls().len(), 2);
+
         Ok(())
     }
 }